@@ -0,0 +1,120 @@
+/* The tidk C API. Generated by cbindgen from src/capi.rs, don't edit. */
+
+#ifndef TIDK_H
+#define TIDK_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ A FASTA or FASTQ file, plain or gzipped.
+ */
+typedef struct TidkFasta TidkFasta;
+
+/*
+ The windows of a FASTA being scanned for a telomeric repeat.
+ */
+typedef struct TidkScan TidkScan;
+
+/*
+ A window of a sequence, and the repeats in it.
+ */
+typedef struct TidkWindow {
+  /*
+   The sequence id, valid until the next call to `tidk_scan_next`
+   or `tidk_scan_free`.
+   */
+  const char *id;
+  /*
+   The 0-based start of the window.
+   */
+  size_t start;
+  /*
+   The end of the window, exclusive.
+   */
+  size_t end;
+  /*
+   Occurrences of the repeat on the forward strand.
+   */
+  size_t forward;
+  /*
+   Occurrences of its reverse complement.
+   */
+  size_t reverse;
+} TidkWindow;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Open a FASTA or FASTQ file. Returns null on error.
+
+ # Safety
+
+ `path` must be a nul terminated string.
+ */
+struct TidkFasta *tidk_fasta_open(const char *path);
+
+/*
+ Scan each sequence of a FASTA for a telomeric repeat, in tiled
+ windows of `window_size` bases. A FASTA can be scanned more than
+ once. Returns null on error.
+
+ # Safety
+
+ `fasta` must be from `tidk_fasta_open`, and `motif` a nul
+ terminated string.
+ */
+struct TidkScan *tidk_scan(const struct TidkFasta *fasta, const char *motif, size_t window_size);
+
+/*
+ Fill `window` with the next window. Returns 1 if there was one, 0
+ at the end of the FASTA, and -1 on error.
+
+ # Safety
+
+ `scan` must be from `tidk_scan`, and `window` point to a
+ `TidkWindow`.
+ */
+int tidk_scan_next(struct TidkScan *scan, struct TidkWindow *window);
+
+/*
+ Free a scan. Null is ignored.
+
+ # Safety
+
+ `scan` must be from `tidk_scan`, and not already freed.
+ */
+void tidk_scan_free(struct TidkScan *scan);
+
+/*
+ Free a FASTA. Null is ignored.
+
+ # Safety
+
+ `fasta` must be from `tidk_fasta_open`, and not already freed.
+ */
+void tidk_fasta_free(struct TidkFasta *fasta);
+
+/*
+ The message of the last error on this thread, or null if there
+ hasn't been one. Valid until the next error.
+ */
+const char *tidk_last_error_message(void);
+
+/*
+ The kind of the last error on this thread, as the exit code of the
+ command line (see `tidk::Error::exit_code`), or 0 if there hasn't
+ been one.
+ */
+int tidk_last_error_code(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TIDK_H */
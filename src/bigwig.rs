@@ -0,0 +1,786 @@
+// The format is described in Kent et al. (2010), BigWig and BigBed:
+// enabling browsing of large distributed datasets. Windows are streamed
+// in as bedGraph style sections compressed with zlib, and the indexes
+// and zoom levels are written when the file is finished, so the windows
+// of a whole genome are never held in memory.
+
+use crate::{windows::WindowCount, Error, Result};
+use flate2::{write::ZlibEncoder, Compression};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const BIGWIG_MAGIC: u32 = 0x888F_FC26;
+const BPT_MAGIC: u32 = 0x78CA_8C91;
+const CIR_TREE_MAGIC: u32 = 0x2468_ACE0;
+/// The format version written by current UCSC tools.
+const VERSION: u16 = 4;
+const HEADER_SIZE: u64 = 64;
+const ZOOM_HEADER_SIZE: u64 = 24;
+const SUMMARY_SIZE: u64 = 40;
+/// Space for this many zoom levels is reserved after the header.
+const MAX_ZOOM_LEVELS: usize = 10;
+/// Items in each compressed block of data.
+const ITEMS_PER_SLOT: usize = 1024;
+/// Children of each node in the chromosome and data indexes.
+const BLOCK_SIZE: usize = 256;
+/// The section type for variable step (start, end, value) items.
+const BEDGRAPH_SECTION: u8 = 1;
+
+/// A summary of the values over some bases, used for the zoom
+/// levels and for the whole file.
+#[derive(Debug, Clone, Copy)]
+struct Summary {
+    bases: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {
+            bases: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+}
+
+impl Summary {
+    /// Add a value covering some bases.
+    fn add(&mut self, bases: u64, value: f64) {
+        self.bases += bases;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value * bases as f64;
+        self.sum_squares += value * value * bases as f64;
+    }
+
+    fn merge(&mut self, other: &Summary) {
+        self.bases += other.bases;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.sum_squares += other.sum_squares;
+    }
+}
+
+/// A zoom level record: the summary of a bin on a chromosome.
+#[derive(Debug, Clone, Copy)]
+struct ZoomRecord {
+    chromosome: u32,
+    start: u32,
+    end: u32,
+    summary: Summary,
+}
+
+/// A compressed block of data and the region it covers, as an
+/// item in the R tree index. Blocks are on a single chromosome, but
+/// the nodes above them can span several.
+#[derive(Debug, Clone, Copy, Default)]
+struct Block {
+    chromosome: u32,
+    start: u32,
+    end_chromosome: u32,
+    end: u32,
+    offset: u64,
+    size: u64,
+}
+
+/// Writes a bigWig file of intervals along each chromosome in turn.
+///
+/// ```no_run
+/// # fn main() -> tidk::Result<()> {
+/// let mut writer = tidk::bigwig::BigWigWriter::create("counts.bw", 10000)?;
+/// writer.add_chromosome("chr1", 25000)?;
+/// writer.add(0, 10000, 3.0)?;
+/// writer.add(10000, 20000, 0.0)?;
+/// writer.add(20000, 25000, 1.0)?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BigWigWriter {
+    file: BufWriter<File>,
+    /// Names and lengths, in the order they were added.
+    chromosomes: Vec<(String, u32)>,
+    /// The items of the block not yet written.
+    section: Vec<(u32, u32, f32)>,
+    blocks: Vec<Block>,
+    data_start: u64,
+    /// The largest block before compression.
+    max_block_size: usize,
+    total: Summary,
+    /// The bin size of the first zoom level.
+    reduction: u32,
+    /// The records of the first zoom level.
+    zoom: Vec<ZoomRecord>,
+}
+
+impl BigWigWriter {
+    /// Create a bigWig file. The first zoom level summarises bins of
+    /// `reduction` bases, and each further level four times as many.
+    pub fn create<P: AsRef<Path>>(path: P, reduction: u32) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        // the header, zoom headers and summary are filled in at the end
+        let data_start = HEADER_SIZE + ZOOM_HEADER_SIZE * MAX_ZOOM_LEVELS as u64 + SUMMARY_SIZE;
+        file.write_all(&vec![0; data_start as usize])?;
+        // and the number of blocks of data
+        file.write_all(&0u64.to_le_bytes())?;
+
+        Ok(Self {
+            file,
+            chromosomes: Vec::new(),
+            section: Vec::new(),
+            blocks: Vec::new(),
+            data_start,
+            max_block_size: 0,
+            total: Summary::default(),
+            reduction: reduction.max(1),
+            zoom: Vec::new(),
+        })
+    }
+
+    /// Start a new chromosome. Following items are on this chromosome.
+    pub fn add_chromosome(&mut self, name: &str, length: u32) -> Result<()> {
+        if self.chromosomes.iter().any(|(n, _)| n == name) {
+            return Err(Error::BigWig(format!(
+                "Chromosome {} was added to the bigWig twice.",
+                name
+            )));
+        }
+        self.flush_section()?;
+        self.chromosomes.push((name.to_string(), length));
+        Ok(())
+    }
+
+    /// Add the value of an interval on the current chromosome. Intervals
+    /// must be added in order, and must not overlap.
+    pub fn add(&mut self, start: u32, end: u32, value: f32) -> Result<()> {
+        let Some(&(_, length)) = self.chromosomes.last() else {
+            return Err(Error::BigWig(
+                "An interval was added to the bigWig before a chromosome.".to_string(),
+            ));
+        };
+        let end = end.min(length);
+        if start >= end {
+            return Ok(());
+        }
+        if let Some(&(_, previous_end, _)) = self.section.last() {
+            if start < previous_end {
+                return Err(Error::BigWig(
+                    "Intervals added to the bigWig must be sorted and not overlap.".to_string(),
+                ));
+            }
+        }
+
+        self.total.add((end - start) as u64, value as f64);
+        self.add_to_zoom(start, end, value as f64);
+        self.section.push((start, end, value));
+        if self.section.len() == ITEMS_PER_SLOT {
+            self.flush_section()?;
+        }
+        Ok(())
+    }
+
+    /// Add an interval to the bins of the first zoom level it overlaps.
+    fn add_to_zoom(&mut self, start: u32, end: u32, value: f64) {
+        let chromosome = self.chromosomes.len() as u32 - 1;
+        let length = self.chromosomes[chromosome as usize].1;
+        let mut position = start;
+        while position < end {
+            let bin_start = position - position % self.reduction;
+            let bin_end = bin_start.saturating_add(self.reduction).min(length);
+            let overlap_end = end.min(bin_end);
+
+            match self.zoom.last_mut() {
+                Some(record) if record.chromosome == chromosome && record.start == bin_start => {
+                    record.summary.add((overlap_end - position) as u64, value)
+                }
+                _ => {
+                    let mut summary = Summary::default();
+                    summary.add((overlap_end - position) as u64, value);
+                    self.zoom.push(ZoomRecord {
+                        chromosome,
+                        start: bin_start,
+                        end: bin_end,
+                        summary,
+                    });
+                }
+            }
+            position = overlap_end;
+        }
+    }
+
+    /// Compress and write the current section.
+    fn flush_section(&mut self) -> Result<()> {
+        let (Some(&first), Some(&last)) = (self.section.first(), self.section.last()) else {
+            return Ok(());
+        };
+        let chromosome = self.chromosomes.len() as u32 - 1;
+
+        let mut data = Vec::with_capacity(24 + self.section.len() * 12);
+        for field in [chromosome, first.0, last.1, 0, 0] {
+            data.extend(field.to_le_bytes());
+        }
+        data.push(BEDGRAPH_SECTION);
+        data.push(0);
+        data.extend((self.section.len() as u16).to_le_bytes());
+        for (start, end, value) in &self.section {
+            data.extend(start.to_le_bytes());
+            data.extend(end.to_le_bytes());
+            data.extend(value.to_le_bytes());
+        }
+
+        let offset = self.file.stream_position()?;
+        let size = self.write_compressed(&data)?;
+        self.blocks.push(Block {
+            chromosome,
+            start: first.0,
+            end_chromosome: chromosome,
+            end: last.1,
+            offset,
+            size,
+        });
+        self.section.clear();
+        Ok(())
+    }
+
+    /// Compress some data into the file, returning its compressed size.
+    fn write_compressed(&mut self, data: &[u8]) -> Result<u64> {
+        self.max_block_size = self.max_block_size.max(data.len());
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        self.file.write_all(&compressed)?;
+        Ok(compressed.len() as u64)
+    }
+
+    /// Write the indexes and zoom levels, and fill in the header.
+    pub fn finish(mut self) -> Result<()> {
+        self.flush_section()?;
+        let data_end = self.file.stream_position()?;
+        let full_index_offset = data_end;
+        let blocks = std::mem::take(&mut self.blocks);
+        self.write_r_tree(&blocks, data_end)?;
+
+        // each zoom level is four times coarser than the last, until
+        // the bins are as long as the longest chromosome
+        let longest = self.chromosomes.iter().map(|c| c.1).max().unwrap_or(0);
+        let mut zoom_headers = Vec::new();
+        let mut records = std::mem::take(&mut self.zoom);
+        let mut reduction = self.reduction;
+        while !records.is_empty() && zoom_headers.len() < MAX_ZOOM_LEVELS {
+            let data_offset = self.file.stream_position()?;
+            self.file.write_all(&(records.len() as u32).to_le_bytes())?;
+            let mut zoom_blocks = Vec::new();
+            for chunk in chunk_by_chromosome(&records) {
+                let mut data = Vec::with_capacity(chunk.len() * 32);
+                for record in chunk {
+                    for field in [
+                        record.chromosome,
+                        record.start,
+                        record.end,
+                        record.summary.bases as u32,
+                    ] {
+                        data.extend(field.to_le_bytes());
+                    }
+                    for field in [
+                        record.summary.min,
+                        record.summary.max,
+                        record.summary.sum,
+                        record.summary.sum_squares,
+                    ] {
+                        data.extend((field as f32).to_le_bytes());
+                    }
+                }
+                let offset = self.file.stream_position()?;
+                let size = self.write_compressed(&data)?;
+                zoom_blocks.push(Block {
+                    chromosome: chunk[0].chromosome,
+                    start: chunk[0].start,
+                    end_chromosome: chunk[0].chromosome,
+                    end: chunk[chunk.len() - 1].end,
+                    offset,
+                    size,
+                });
+            }
+            let index_end = self.file.stream_position()?;
+            let index_offset = index_end;
+            self.write_r_tree(&zoom_blocks, index_end)?;
+            zoom_headers.push((reduction, data_offset, index_offset));
+
+            if reduction as u64 >= longest as u64 || reduction > u32::MAX / 4 {
+                break;
+            }
+            reduction *= 4;
+            records = coarsen(&records, reduction, &self.chromosomes);
+        }
+
+        let chromosome_tree_offset = self.file.stream_position()?;
+        self.write_chromosome_tree()?;
+
+        // now fill in the start of the file
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut header = Vec::with_capacity(self.data_start as usize);
+        header.extend(BIGWIG_MAGIC.to_le_bytes());
+        header.extend(VERSION.to_le_bytes());
+        header.extend((zoom_headers.len() as u16).to_le_bytes());
+        header.extend(chromosome_tree_offset.to_le_bytes());
+        header.extend(self.data_start.to_le_bytes());
+        header.extend(full_index_offset.to_le_bytes());
+        // no fields or autoSql, as in any bigWig
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        let summary_offset = HEADER_SIZE + ZOOM_HEADER_SIZE * MAX_ZOOM_LEVELS as u64;
+        header.extend(summary_offset.to_le_bytes());
+        header.extend((self.max_block_size as u32).to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+
+        for (reduction, data_offset, index_offset) in &zoom_headers {
+            header.extend(reduction.to_le_bytes());
+            header.extend(0u32.to_le_bytes());
+            header.extend(data_offset.to_le_bytes());
+            header.extend(index_offset.to_le_bytes());
+        }
+        header.resize(summary_offset as usize, 0);
+
+        let total = if self.total.bases == 0 {
+            Summary {
+                min: 0.0,
+                max: 0.0,
+                ..self.total
+            }
+        } else {
+            self.total
+        };
+        header.extend(total.bases.to_le_bytes());
+        for field in [total.min, total.max, total.sum, total.sum_squares] {
+            header.extend(field.to_le_bytes());
+        }
+        header.extend((blocks.len() as u64).to_le_bytes());
+        self.file.write_all(&header)?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Write the R tree indexing blocks of data by their region.
+    fn write_r_tree(&mut self, blocks: &[Block], data_end: u64) -> Result<()> {
+        // the levels of the tree from the leaves up, as the region
+        // each node covers and its range of children
+        let mut levels: Vec<Vec<(Block, std::ops::Range<usize>)>> = Vec::new();
+        let mut nodes: Vec<Block> = blocks.to_vec();
+        loop {
+            let level: Vec<(Block, std::ops::Range<usize>)> = (0..nodes.len().max(1))
+                .step_by(BLOCK_SIZE)
+                .map(|i| {
+                    let children = i..(i + BLOCK_SIZE).min(nodes.len());
+                    (bounds(&nodes[children.clone()]), children)
+                })
+                .collect();
+            nodes = level.iter().map(|(bounds, _)| *bounds).collect();
+            levels.push(level);
+            if nodes.len() == 1 {
+                break;
+            }
+        }
+
+        let root = nodes[0];
+        for field in [CIR_TREE_MAGIC, BLOCK_SIZE as u32] {
+            self.file.write_all(&field.to_le_bytes())?;
+        }
+        self.file.write_all(&(blocks.len() as u64).to_le_bytes())?;
+        write_region(&mut self.file, &root)?;
+        self.file.write_all(&data_end.to_le_bytes())?;
+        self.file
+            .write_all(&(ITEMS_PER_SLOT as u32).to_le_bytes())?;
+        self.file.write_all(&0u32.to_le_bytes())?;
+
+        // write from the root down, so each node knows where the next
+        // level starts
+        let mut offset = self.file.stream_position()?;
+        for (depth, level) in levels.iter().enumerate().rev() {
+            let leaf = depth == 0;
+            let level_size: u64 = level
+                .iter()
+                .map(|(_, children)| 4 + children.len() as u64 * if leaf { 32 } else { 24 })
+                .sum();
+            let mut child_offset = offset + level_size;
+            for (_, children) in level {
+                self.file.write_all(&[leaf as u8, 0])?;
+                self.file
+                    .write_all(&(children.len() as u16).to_le_bytes())?;
+                for child in children.clone() {
+                    if leaf {
+                        let block = blocks[child];
+                        write_region(&mut self.file, &block)?;
+                        self.file.write_all(&block.offset.to_le_bytes())?;
+                        self.file.write_all(&block.size.to_le_bytes())?;
+                    } else {
+                        let (region, grandchildren) = &levels[depth - 1][child];
+                        write_region(&mut self.file, region)?;
+                        self.file.write_all(&child_offset.to_le_bytes())?;
+                        child_offset +=
+                            4 + grandchildren.len() as u64 * if depth == 1 { 32 } else { 24 };
+                    }
+                }
+            }
+            offset += level_size;
+        }
+        Ok(())
+    }
+
+    /// Write the B+ tree of chromosome names to their IDs and lengths.
+    fn write_chromosome_tree(&mut self) -> Result<()> {
+        let mut chromosomes: Vec<(&str, u32, u32)> = self
+            .chromosomes
+            .iter()
+            .enumerate()
+            .map(|(id, (name, length))| (name.as_str(), id as u32, *length))
+            .collect();
+        // the tree is searched by the bytes of the name
+        chromosomes.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        let key_size = chromosomes
+            .iter()
+            .map(|c| c.0.len())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let block_size = BLOCK_SIZE.min(chromosomes.len()).max(1);
+        let item_count = chromosomes.len();
+
+        for field in [BPT_MAGIC, block_size as u32, key_size as u32, 8] {
+            self.file.write_all(&field.to_le_bytes())?;
+        }
+        self.file.write_all(&(item_count as u64).to_le_bytes())?;
+        self.file.write_all(&0u64.to_le_bytes())?;
+
+        let key = |name: &str| {
+            let mut key = name.as_bytes().to_vec();
+            key.resize(key_size, 0);
+            key
+        };
+
+        let mut levels = 1;
+        let mut n = item_count;
+        while n > block_size {
+            n = n.div_ceil(block_size);
+            levels += 1;
+        }
+
+        // nodes are padded to the block size, and child offsets and
+        // values are both eight bytes, so every node is the same size
+        let node_bytes = 4 + block_size * (key_size + 8);
+        let mut offset = self.file.stream_position()?;
+        for level in (1..levels).rev() {
+            let slot_size = block_size.pow(level as u32);
+            let node_size = slot_size * block_size;
+            let node_count = item_count.div_ceil(node_size);
+            let level_end = offset + (node_count * node_bytes) as u64;
+            let mut next_child = level_end;
+            for i in (0..item_count).step_by(node_size) {
+                let count = (item_count - i).div_ceil(slot_size).min(block_size);
+                self.file.write_all(&[0, 0])?;
+                self.file.write_all(&(count as u16).to_le_bytes())?;
+                for j in 0..count {
+                    self.file
+                        .write_all(&key(chromosomes[i + j * slot_size].0))?;
+                    self.file.write_all(&next_child.to_le_bytes())?;
+                    next_child += node_bytes as u64;
+                }
+                self.file
+                    .write_all(&vec![0; (block_size - count) * (key_size + 8)])?;
+            }
+            offset = level_end;
+        }
+
+        for leaf in chromosomes.chunks(block_size) {
+            self.file.write_all(&[1, 0])?;
+            self.file.write_all(&(leaf.len() as u16).to_le_bytes())?;
+            for (name, id, length) in leaf {
+                self.file.write_all(&key(name))?;
+                self.file.write_all(&id.to_le_bytes())?;
+                self.file.write_all(&length.to_le_bytes())?;
+            }
+            self.file
+                .write_all(&vec![0; (block_size - leaf.len()) * (key_size + 8)])?;
+        }
+        Ok(())
+    }
+}
+
+/// The region covered by some blocks (or nodes) in order.
+fn bounds(blocks: &[Block]) -> Block {
+    match (blocks.first(), blocks.last()) {
+        (Some(first), Some(last)) => Block {
+            chromosome: first.chromosome,
+            start: first.start,
+            end_chromosome: last.end_chromosome,
+            end: last.end,
+            ..Block::default()
+        },
+        _ => Block::default(),
+    }
+}
+
+/// Write the start and end of a region in the R tree.
+fn write_region<W: Write>(file: &mut W, region: &Block) -> Result<()> {
+    for field in [
+        region.chromosome,
+        region.start,
+        region.end_chromosome,
+        region.end,
+    ] {
+        file.write_all(&field.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Split zoom records into blocks on a single chromosome.
+fn chunk_by_chromosome(records: &[ZoomRecord]) -> Vec<&[ZoomRecord]> {
+    records
+        .chunk_by(|a, b| a.chromosome == b.chromosome)
+        .flat_map(|chunk| chunk.chunks(ITEMS_PER_SLOT))
+        .collect()
+}
+
+/// Merge zoom records into bins of a greater reduction.
+fn coarsen(
+    records: &[ZoomRecord],
+    reduction: u32,
+    chromosomes: &[(String, u32)],
+) -> Vec<ZoomRecord> {
+    let mut coarse: Vec<ZoomRecord> = Vec::new();
+    for record in records {
+        let start = record.start - record.start % reduction;
+        match coarse.last_mut() {
+            Some(last) if last.chromosome == record.chromosome && last.start == start => {
+                last.summary.merge(&record.summary);
+            }
+            _ => coarse.push(ZoomRecord {
+                chromosome: record.chromosome,
+                start,
+                end: start
+                    .saturating_add(reduction)
+                    .min(chromosomes[record.chromosome as usize].1),
+                summary: record.summary,
+            }),
+        }
+    }
+    coarse
+}
+
+/// The file of a bigWig track, named by the track if there's more
+/// than one.
+fn track_name(prefix: &str, track: Option<&str>) -> PathBuf {
+    match track {
+        Some(track) => PathBuf::from(format!("{}_{}.bw", prefix, track)),
+        None => PathBuf::from(format!("{}.bw", prefix)),
+    }
+}
+
+/// The bigWig tracks of the window counts of one telomeric repeat:
+/// either a single track of the total (forward + reverse) count, or
+/// separate forward, reverse and total tracks. With `normalise`, the
+/// tracks hold repeat densities rather than counts.
+pub struct CountTracks {
+    forward: Option<BigWigWriter>,
+    reverse: Option<BigWigWriter>,
+    total: BigWigWriter,
+    window_size: usize,
+    step: usize,
+    normalise: bool,
+}
+
+impl CountTracks {
+    /// The files written for `prefix`: `{prefix}.bw`, or
+    /// `{prefix}_forward.bw`, `{prefix}_reverse.bw` and `{prefix}_total.bw`
+    /// if `stranded`.
+    pub fn file_names(prefix: &str, stranded: bool) -> Vec<PathBuf> {
+        if stranded {
+            ["forward", "reverse", "total"]
+                .into_iter()
+                .map(|track| track_name(prefix, Some(track)))
+                .collect()
+        } else {
+            vec![track_name(prefix, None)]
+        }
+    }
+
+    /// Create the files named by [`CountTracks::file_names()`].
+    pub fn create(
+        prefix: &str,
+        stranded: bool,
+        window_size: usize,
+        step: usize,
+        normalise: bool,
+    ) -> Result<Self> {
+        // the first zoom level summarises four windows
+        let reduction = u32::try_from(window_size.saturating_mul(4)).unwrap_or(u32::MAX);
+        let (forward, reverse) = if stranded {
+            (
+                Some(BigWigWriter::create(
+                    track_name(prefix, Some("forward")),
+                    reduction,
+                )?),
+                Some(BigWigWriter::create(
+                    track_name(prefix, Some("reverse")),
+                    reduction,
+                )?),
+            )
+        } else {
+            (None, None)
+        };
+        let total =
+            BigWigWriter::create(track_name(prefix, stranded.then_some("total")), reduction)?;
+        Ok(Self {
+            forward,
+            reverse,
+            total,
+            window_size,
+            step,
+            normalise,
+        })
+    }
+
+    fn writers(&mut self) -> impl Iterator<Item = &mut BigWigWriter> {
+        self.forward
+            .iter_mut()
+            .chain(self.reverse.iter_mut())
+            .chain(std::iter::once(&mut self.total))
+    }
+
+    /// Start a new chromosome, with its length from the FASTA record.
+    pub fn add_chromosome(&mut self, name: &str, length: usize) -> Result<()> {
+        let Ok(length) = u32::try_from(length) else {
+            return Err(Error::BigWig(format!(
+                "Chromosome {} is too long ({} bp) to be written to bigWig.",
+                name, length
+            )));
+        };
+        for writer in self.writers() {
+            writer.add_chromosome(name, length)?;
+        }
+        Ok(())
+    }
+
+    /// Add a window on the current chromosome. Densities are left out
+    /// for windows which are all N.
+    pub fn add(&mut self, window: &WindowCount) -> Result<()> {
+        let value = |occurrences: usize| -> Option<f32> {
+            if self.normalise {
+                window
+                    .density(occurrences, window.motif.len())
+                    .map(|density| density as f32)
+            } else {
+                Some(occurrences as f32)
+            }
+        };
+        let (Some(forward), Some(reverse), Some(total)) = (
+            value(window.forward),
+            value(window.reverse),
+            value(window.forward + window.reverse),
+        ) else {
+            return Ok(());
+        };
+
+        // checked against the chromosome length already
+        let (start, end) = window.track_interval(self.window_size, self.step);
+        let (start, end) = (start as u32, end as u32);
+        if let Some(writer) = self.forward.as_mut() {
+            writer.add(start, end, forward)?;
+        }
+        if let Some(writer) = self.reverse.as_mut() {
+            writer.add(start, end, reverse)?;
+        }
+        self.total.add(start, end, total)
+    }
+
+    /// Finish writing every track.
+    pub fn finish(self) -> Result<()> {
+        if let Some(writer) = self.forward {
+            writer.finish()?;
+        }
+        if let Some(writer) = self.reverse {
+            writer.finish()?;
+        }
+        self.total.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_bigwig_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("round_trip.bw");
+        let mut writer = BigWigWriter::create(&path, 20).unwrap();
+        writer.add_chromosome("chr2", 25).unwrap();
+        writer.add(0, 10, 3.0).unwrap();
+        writer.add(10, 20, 0.0).unwrap();
+        // clipped to the chromosome
+        writer.add(20, 30, 1.0).unwrap();
+        writer.add_chromosome("chr1", 10).unwrap();
+        writer.add(0, 10, 2.0).unwrap();
+        assert!(writer.add(5, 10, 2.0).is_err());
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(u32_at(&bytes, 0), BIGWIG_MAGIC);
+        // zoom levels of 20 and 80 bases
+        assert_eq!(u16::from_le_bytes([bytes[6], bytes[7]]), 2);
+        assert_eq!(u32_at(&bytes, 64), 20);
+
+        // the total summary
+        let summary = (HEADER_SIZE + ZOOM_HEADER_SIZE * MAX_ZOOM_LEVELS as u64) as usize;
+        assert_eq!(u64_at(&bytes, summary), 35);
+        assert_eq!(
+            f64::from_le_bytes(bytes[summary + 24..summary + 32].try_into().unwrap()),
+            55.0
+        );
+
+        // two blocks of data, one for each chromosome
+        let data = u64_at(&bytes, 16) as usize;
+        assert_eq!(u64_at(&bytes, data), 2);
+
+        // the first block, from the leaf of the R tree
+        let index = u64_at(&bytes, 24) as usize;
+        assert_eq!(u32_at(&bytes, index), CIR_TREE_MAGIC);
+        let leaf = index + 48;
+        assert_eq!(bytes[leaf], 1);
+        let offset = u64_at(&bytes, leaf + 4 + 16) as usize;
+        let size = u64_at(&bytes, leaf + 4 + 24) as usize;
+        let mut block = Vec::new();
+        ZlibDecoder::new(&bytes[offset..offset + size])
+            .read_to_end(&mut block)
+            .unwrap();
+        assert_eq!(u32_at(&block, 0), 0);
+        assert_eq!(u16::from_le_bytes([block[22], block[23]]), 3);
+        // the last item is clipped to the chromosome
+        assert_eq!(u32_at(&block, 24 + 24 + 4), 25);
+
+        // the chromosome tree is sorted by name
+        let tree = u64_at(&bytes, 8) as usize;
+        assert_eq!(u32_at(&bytes, tree), BPT_MAGIC);
+        assert_eq!(&bytes[tree + 36..tree + 40], b"chr1");
+        assert_eq!(u32_at(&bytes, tree + 40), 1);
+        assert_eq!(u32_at(&bytes, tree + 44), 10);
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use crate::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -28,13 +28,14 @@ pub struct TelomereRepeatRow {
 
 // Function to get the database path
 pub fn get_database_path() -> Result<PathBuf> {
-    let base_dir =
-        dirs::data_dir().context("Could not determine the base directory for application data")?;
+    let base_dir = dirs::data_dir().ok_or_else(|| {
+        Error::Database("Could not determine the base directory for application data.".to_string())
+    })?;
 
     // Create path for the application directory within the data dir
     let app_dir = base_dir.join("tidk");
     // Ensure that the directory exists
-    fs::create_dir_all(&app_dir).context("Failed to create application data directory")?;
+    fs::create_dir_all(&app_dir)?;
 
     Ok(app_dir.join(TIDK_DATABASE)) // Path to the dataset file
 }
@@ -52,27 +53,37 @@ pub fn fetch_and_save_data() -> Result<()> {
     let client = reqwest::blocking::Client::new();
     let mut headers = reqwest::header::HeaderMap::new();
 
-    headers.insert("authorization", "<authorization>".parse().unwrap());
-    headers.insert("user-agent", "CUSTOM_NAME/1.0".parse().unwrap());
+    headers.insert(
+        "authorization",
+        reqwest::header::HeaderValue::from_static("<authorization>"),
+    );
+    headers.insert(
+        "user-agent",
+        reqwest::header::HeaderValue::from_static("CUSTOM_NAME/1.0"),
+    );
 
     let response = client.get(url).headers(headers).send()?;
     let response_json = response.json::<serde_json::Value>()?;
 
     // FIXME: use this sha later on to do better version control?
+    let missing = |field: &str| Error::Database(format!("No {} found in response.", field));
     let _sha = response_json["sha"]
         .as_str()
-        .context("No sha found in response")?;
+        .ok_or_else(|| missing("sha"))?;
     let mut content = response_json["content"]
         .as_str()
-        .context("No content found in response")?
+        .ok_or_else(|| missing("content"))?
         .to_string();
 
     // whitespaces were messing up base64 decoding
     content.retain(|c| !c.is_whitespace());
 
     // decode the content. I am assuming it's standard base64 encoding.
-    let data_u8 = BASE64_STANDARD.decode(content)?;
-    let data = String::from_utf8(data_u8)?;
+    let data_u8 = BASE64_STANDARD
+        .decode(content)
+        .map_err(|e| Error::Database(format!("Could not decode the database: {}", e)))?;
+    let data = String::from_utf8(data_u8)
+        .map_err(|e| Error::Database(format!("Could not decode the database: {}", e)))?;
 
     // write the data to file - the name of which is the SHA commit
     let database_path = get_database_path()?;
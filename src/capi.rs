@@ -0,0 +1,213 @@
+use crate::{
+    error::check_motif,
+    input,
+    windows::{SoftMask, WindowCounts},
+    Error, Result,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    path::PathBuf,
+    ptr,
+};
+
+thread_local! {
+    /// The last error on this thread, with its code.
+    static LAST_ERROR: RefCell<Option<(c_int, CString)>> = const { RefCell::new(None) };
+}
+
+/// Keep an error for `tidk_last_error_message`.
+fn set_last_error(e: Error) {
+    // the message can't contain a nul, as the paths and motifs in
+    // it came from C strings
+    let message = CString::new(e.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((e.exit_code(), message)));
+}
+
+/// A C string argument, which must not be null.
+///
+/// # Safety
+///
+/// `s` must be null, or a nul terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(Error::InvalidOption(format!("The {} is null.", name)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| Error::InvalidOption(format!("The {} is not valid UTF-8.", name)))
+}
+
+/// A FASTA or FASTQ file, plain or gzipped.
+pub struct TidkFasta {
+    path: PathBuf,
+}
+
+/// The windows of a FASTA being scanned for a telomeric repeat.
+pub struct TidkScan {
+    windows: WindowCounts,
+    /// The id of the last window returned.
+    id: CString,
+}
+
+/// A window of a sequence, and the repeats in it.
+#[repr(C)]
+pub struct TidkWindow {
+    /// The sequence id, valid until the next call to `tidk_scan_next`
+    /// or `tidk_scan_free`.
+    pub id: *const c_char,
+    /// The 0-based start of the window.
+    pub start: usize,
+    /// The end of the window, exclusive.
+    pub end: usize,
+    /// Occurrences of the repeat on the forward strand.
+    pub forward: usize,
+    /// Occurrences of its reverse complement.
+    pub reverse: usize,
+}
+
+/// Open a FASTA or FASTQ file. Returns null on error.
+///
+/// # Safety
+///
+/// `path` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tidk_fasta_open(path: *const c_char) -> *mut TidkFasta {
+    let open = || -> Result<TidkFasta> {
+        let path = PathBuf::from(str_arg(path, "path")?);
+        // check the file can be read now, rather than on the first scan
+        let _ = input::read_sequences(&path, None)?;
+        Ok(TidkFasta { path })
+    };
+    match open() {
+        Ok(fasta) => Box::into_raw(Box::new(fasta)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Scan each sequence of a FASTA for a telomeric repeat, in tiled
+/// windows of `window_size` bases. A FASTA can be scanned more than
+/// once. Returns null on error.
+///
+/// # Safety
+///
+/// `fasta` must be from `tidk_fasta_open`, and `motif` a nul
+/// terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tidk_scan(
+    fasta: *const TidkFasta,
+    motif: *const c_char,
+    window_size: usize,
+) -> *mut TidkScan {
+    let scan = || -> Result<TidkScan> {
+        let fasta = fasta
+            .as_ref()
+            .ok_or_else(|| Error::InvalidOption("The FASTA is null.".to_string()))?;
+        let motif = str_arg(motif, "motif")?;
+        check_motif(motif)?;
+        if window_size == 0 {
+            return Err(Error::InvalidOption(
+                "The window size must be greater than zero.".to_string(),
+            ));
+        }
+        let (_, sequences) = input::read_sequences(&fasta.path, None)?;
+        Ok(TidkScan {
+            windows: WindowCounts::new(
+                sequences,
+                vec![motif.to_string()],
+                window_size,
+                window_size,
+                SoftMask::Include,
+            ),
+            id: CString::default(),
+        })
+    };
+    match scan() {
+        Ok(scan) => Box::into_raw(Box::new(scan)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Fill `window` with the next window. Returns 1 if there was one, 0
+/// at the end of the FASTA, and -1 on error.
+///
+/// # Safety
+///
+/// `scan` must be from `tidk_scan`, and `window` point to a
+/// `TidkWindow`.
+#[no_mangle]
+pub unsafe extern "C" fn tidk_scan_next(scan: *mut TidkScan, window: *mut TidkWindow) -> c_int {
+    let (Some(scan), Some(window)) = (scan.as_mut(), window.as_mut()) else {
+        set_last_error(Error::InvalidOption(
+            "The scan or window is null.".to_string(),
+        ));
+        return -1;
+    };
+    match scan.windows.next() {
+        Some(Ok(count)) => {
+            scan.id = CString::new(count.id).unwrap_or_default();
+            *window = TidkWindow {
+                id: scan.id.as_ptr(),
+                start: count.start,
+                end: count.end,
+                forward: count.forward,
+                reverse: count.reverse,
+            };
+            1
+        }
+        Some(Err(e)) => {
+            set_last_error(e);
+            -1
+        }
+        None => 0,
+    }
+}
+
+/// Free a scan. Null is ignored.
+///
+/// # Safety
+///
+/// `scan` must be from `tidk_scan`, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn tidk_scan_free(scan: *mut TidkScan) {
+    if !scan.is_null() {
+        drop(Box::from_raw(scan));
+    }
+}
+
+/// Free a FASTA. Null is ignored.
+///
+/// # Safety
+///
+/// `fasta` must be from `tidk_fasta_open`, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn tidk_fasta_free(fasta: *mut TidkFasta) {
+    if !fasta.is_null() {
+        drop(Box::from_raw(fasta));
+    }
+}
+
+/// The message of the last error on this thread, or null if there
+/// hasn't been one. Valid until the next error.
+#[no_mangle]
+pub extern "C" fn tidk_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |(_, message)| message.as_ptr())
+    })
+}
+
+/// The kind of the last error on this thread, as the exit code of the
+/// command line (see `tidk::Error::exit_code`), or 0 if there hasn't
+/// been one.
+#[no_mangle]
+pub extern "C" fn tidk_last_error_code() -> c_int {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(0, |(code, _)| *code))
+}
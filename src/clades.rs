@@ -1,4 +1,3 @@
-use anyhow::Result;
 use std::fmt::{self, Display};
 use tabled::{
     settings::{
@@ -8,12 +7,21 @@ use tabled::{
     Table, Tabled,
 };
 
-use crate::build::{get_database_path, TelomereRepeatRow};
+use crate::{
+    build::{get_database_path, TelomereRepeatRow},
+    Error, Result,
+};
 
 /// A telomeric repeat sequence, or sequences.
 #[derive(Debug, Clone)]
 pub struct Seq(pub Vec<String>);
 
+impl Default for Seq {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Seq {
     /// Create a new empty sequence.
     pub fn new() -> Self {
@@ -85,17 +93,25 @@ impl TelomereSeq {
     }
 }
 
+/// The database is missing, or can't be read.
+fn database_error(e: csv::Error) -> Error {
+    Error::Database(format!(
+        "Could not read the database, run `tidk build` to fetch it: {}",
+        e
+    ))
+}
+
 /// Read from a csv file containing all the clades
 /// and only return a list of clades.
 pub fn get_clades() -> Result<Vec<String>> {
     // open from disk
     let path = get_database_path()?;
-    let mut rdr = csv::Reader::from_path(path)?;
+    let mut rdr = csv::Reader::from_path(path).map_err(database_error)?;
 
     let mut out = vec![];
 
     for result in rdr.deserialize() {
-        let record: TelomereRepeatRow = result?;
+        let record: TelomereRepeatRow = result.map_err(database_error)?;
         // just the orders
         let order = record.order;
         out.push(order);
@@ -103,7 +119,7 @@ pub fn get_clades() -> Result<Vec<String>> {
 
     // remove duplicates and empty strings
     out.dedup();
-    out.retain(|e| e != "");
+    out.retain(|e| !e.is_empty());
 
     Ok(out)
 }
@@ -113,7 +129,7 @@ pub fn get_clades() -> Result<Vec<String>> {
 pub fn return_telomere_sequence(clade: String) -> Result<TelomereSeq> {
     let path = get_database_path()?;
     // read the csv file
-    let mut rdr = csv::Reader::from_path(path)?;
+    let mut rdr = csv::Reader::from_path(path).map_err(database_error)?;
 
     // iterate over records, if they match the clade
     // push all the sequences into a TelomereSeq object
@@ -121,7 +137,7 @@ pub fn return_telomere_sequence(clade: String) -> Result<TelomereSeq> {
     let mut telomere_seq = TelomereSeq::new(clade.clone(), Seq::new());
 
     for result in rdr.deserialize() {
-        let record: TelomereRepeatRow = result?;
+        let record: TelomereRepeatRow = result.map_err(database_error)?;
         if record.order == clade {
             telomere_seq.push(record.telomeric_repeat);
         }
@@ -129,6 +145,12 @@ pub fn return_telomere_sequence(clade: String) -> Result<TelomereSeq> {
 
     // set the length
     telomere_seq.set_length();
+    if telomere_seq.length == 0 {
+        return Err(Error::Database(format!(
+            "The clade {} is not in the database.",
+            clade
+        )));
+    }
 
     // return the telomeric sequence
     Ok(telomere_seq)
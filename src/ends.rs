@@ -0,0 +1,288 @@
+use crate::{
+    input,
+    reads::{self, ArrayOptions, TerminalArray},
+    Error, Result,
+};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// How the flanks of telomeric reads are matched to contig ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// The bases of a read next to its telomeric array which are matched.
+    pub flank: usize,
+    /// The bases at each end of a contig which are matched against.
+    pub window: usize,
+    /// The k-mer length, at most 32.
+    pub k: usize,
+    /// The fewest k-mers a flank must share with a contig end.
+    pub min_shared: usize,
+}
+
+/// One end of a contig, and the telomeric reads matched to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContigEnd {
+    pub contig: String,
+    /// "start" or "end" of the contig.
+    pub end: &'static str,
+    pub contig_length: usize,
+    /// The length of the telomeric array already at this end (if any).
+    pub array_length: usize,
+    /// The array lengths of the reads matched to this end.
+    pub read_arrays: Vec<usize>,
+}
+
+impl ContigEnd {
+    /// The median array length of the matched reads.
+    pub fn median_read_array(&self) -> Option<f64> {
+        let mut lengths = self.read_arrays.clone();
+        reads::LengthSummary::new(&mut lengths).median
+    }
+
+    /// How much longer the telomere is in the reads than in the
+    /// contig, from the median array length of the matched reads.
+    pub fn missing_length(&self) -> Option<usize> {
+        self.median_read_array()
+            .map(|median| (median as usize).saturating_sub(self.array_length))
+    }
+}
+
+/// The k-mers which are unique to one contig end, with the index of
+/// that end. K-mers shared between ends (e.g. of the telomeric repeat
+/// itself) are kept as `None`, so they can't support either.
+pub struct EndIndex {
+    options: MatchOptions,
+    kmers: HashMap<u64, Option<usize>>,
+    pub ends: Vec<ContigEnd>,
+}
+
+impl EndIndex {
+    /// Index the terminal windows of each contig of an assembly.
+    pub fn build(
+        assembly: &Path,
+        telomeric_repeat: &str,
+        array_options: &ArrayOptions,
+        options: MatchOptions,
+    ) -> Result<Self> {
+        if options.k == 0 || options.k > 32 {
+            return Err(Error::InvalidOption(
+                "The k-mer length must be from 1 to 32.".to_string(),
+            ));
+        }
+        let mut index = Self {
+            options,
+            kmers: HashMap::new(),
+            ends: Vec::new(),
+        };
+        let (_, sequences) = input::read_sequences(assembly, None)?;
+        for sequence in sequences {
+            let sequence = sequence?;
+            let length = sequence.seq.len();
+            // short contigs are split between their two ends
+            let windows = [
+                ("start", 0..options.window.min(length / 2)),
+                (
+                    "end",
+                    length.saturating_sub(options.window).max(length / 2)..length,
+                ),
+            ];
+            for (end, range) in windows {
+                let window = &sequence.seq[range];
+                let array_length = reads::terminal_arrays(window, telomeric_repeat, array_options)?
+                    .iter()
+                    .find(|array| array.read_end == end)
+                    .map_or(0, TerminalArray::length);
+                let end_index = index.ends.len();
+                for kmer in canonical_kmers(window, options.k) {
+                    index
+                        .kmers
+                        .entry(kmer)
+                        .and_modify(|owner| {
+                            if *owner != Some(end_index) {
+                                *owner = None;
+                            }
+                        })
+                        .or_insert(Some(end_index));
+                }
+                index.ends.push(ContigEnd {
+                    contig: sequence.id.clone(),
+                    end,
+                    contig_length: length,
+                    array_length,
+                    read_arrays: Vec::new(),
+                });
+            }
+        }
+        if index.ends.is_empty() {
+            return Err(Error::EmptyInput(assembly.to_path_buf()));
+        }
+        Ok(index)
+    }
+
+    /// The contig end sharing the most k-mers with a sequence, if it
+    /// shares enough and no other end shares as many.
+    pub fn best_end(&self, sequence: &[u8]) -> Option<usize> {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for kmer in canonical_kmers(sequence, self.options.k) {
+            if let Some(Some(end)) = self.kmers.get(&kmer) {
+                *shared.entry(*end).or_default() += 1;
+            }
+        }
+        let most = shared.values().copied().max()?;
+        let mut best = shared.iter().filter(|(_, &count)| count == most);
+        match (best.next(), best.next()) {
+            (Some((&end, _)), None) if most >= self.options.min_shared => Some(end),
+            _ => None,
+        }
+    }
+
+    /// Match the flank of a read's telomeric array, the bases next to it
+    /// on the inner side, to a contig end. Returns the index of the end.
+    pub fn add_read(&mut self, read: &[u8], array: &TerminalArray) -> Option<usize> {
+        let flank = if array.read_end == "start" {
+            &read[array.end..(array.end + self.options.flank).min(read.len())]
+        } else {
+            &read[array.start.saturating_sub(self.options.flank)..array.start]
+        };
+        let end = self.best_end(flank)?;
+        self.ends[end].read_arrays.push(array.length());
+        Some(end)
+    }
+
+    /// Write a row for each contig end.
+    pub fn write_tsv<W: Write>(&self, file: &mut W) -> Result<()> {
+        writeln!(
+            file,
+            "contig\tcontig_end\tcontig_length\tcontig_array_length\tsupporting_reads\tmedian_read_array_length\tmissing_telomere_length"
+        )?;
+        for end in &self.ends {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                end.contig,
+                end.end,
+                end.contig_length,
+                end.array_length,
+                end.read_arrays.len(),
+                end.median_read_array()
+                    .map(|median| format!("{:.0}", median))
+                    .unwrap_or_default(),
+                end.missing_length()
+                    .map(|missing| missing.to_string())
+                    .unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The canonical k-mers of a sequence, each the lesser of its forward
+/// and reverse complement 2-bit encoding. K-mers with bases other than
+/// A, C, G or T are skipped.
+fn canonical_kmers(sequence: &[u8], k: usize) -> Vec<u64> {
+    let mask = if k == 32 {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    };
+    let mut kmers = Vec::new();
+    let (mut forward, mut reverse, mut valid) = (0u64, 0u64, 0);
+    for base in sequence {
+        let code = match base {
+            b'A' | b'a' => 0,
+            b'C' | b'c' => 1,
+            b'G' | b'g' => 2,
+            b'T' | b't' => 3,
+            _ => {
+                valid = 0;
+                continue;
+            }
+        };
+        forward = ((forward << 2) | code) & mask;
+        reverse = (reverse >> 2) | ((3 - code) << (2 * (k - 1)));
+        valid += 1;
+        if valid >= k {
+            kmers.push(forward.min(reverse));
+        }
+    }
+    kmers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn test_canonical_kmers() {
+        let kmers = canonical_kmers(b"ACGTTNacg", 3);
+        // ACG, CGT, GTT; the N breaks the run, then acg
+        assert_eq!(kmers.len(), 4);
+        // ACG and CGT are reverse complements
+        assert_eq!(kmers[0], kmers[1]);
+        assert_eq!(kmers[0], kmers[3]);
+        let reverse = utils::reverse_complement("GATTACAGATTACA");
+        assert_eq!(
+            canonical_kmers(b"GATTACAGATTACA", 5),
+            canonical_kmers(reverse.as_bytes(), 5)
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_end_index() {
+        let random = |seed: u64, length: usize| -> String {
+            let mut state = seed;
+            (0..length)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    b"ACGT"[(state >> 62) as usize] as char
+                })
+                .collect()
+        };
+        // chr1 has a short telomere at its start, chr2 none
+        let chr1 = format!("{}{}", "CCCTAA".repeat(5), random(1, 400));
+        let chr2 = random(2, 400);
+        let dir = tempfile::tempdir().unwrap();
+        let assembly = dir.path().join("assembly.fa");
+        std::fs::write(&assembly, format!(">chr1\n{}\n>chr2\n{}\n", chr1, chr2)).unwrap();
+
+        let array_options = ArrayOptions {
+            max_distance: 10,
+            max_gap: 5,
+            min_length: 12,
+        };
+        let options = MatchOptions {
+            flank: 50,
+            window: 100,
+            k: 15,
+            min_shared: 10,
+        };
+        let mut index = EndIndex::build(&assembly, "TTAGGG", &array_options, options).unwrap();
+        assert_eq!(index.ends.len(), 4);
+        assert_eq!(index.ends[0].array_length, 30);
+
+        // a longer telomere, then the start of chr1
+        let read = format!("{}{}", "CCCTAA".repeat(20), &chr1[30..130]);
+        let arrays = reads::terminal_arrays(read.as_bytes(), "TTAGGG", &array_options).unwrap();
+        assert_eq!(index.add_read(read.as_bytes(), &arrays[0]), Some(0));
+
+        // the end of chr2, read from the other strand
+        let read = format!(
+            "{}{}",
+            "CCCTAA".repeat(10),
+            utils::reverse_complement(&chr2[300..])
+        );
+        let arrays = reads::terminal_arrays(read.as_bytes(), "TTAGGG", &array_options).unwrap();
+        assert_eq!(index.add_read(read.as_bytes(), &arrays[0]), Some(3));
+
+        assert_eq!(index.ends[0].missing_length(), Some(90));
+        assert_eq!(index.ends[1].missing_length(), None);
+        assert_eq!(index.ends[3].missing_length(), Some(60));
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+/// The errors of `tidk`, by what went wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reading or writing a file.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A malformed FASTA or FASTQ file.
+    #[error("{0}")]
+    Fasta(String),
+    /// The clade database is missing, malformed or couldn't be fetched.
+    #[error("{0}")]
+    Database(String),
+    /// A telomeric repeat which isn't DNA.
+    #[error("The telomeric repeat {0:?} must be made of A, C, G and T.")]
+    InvalidMotif(String),
+    /// An input file with no records in it.
+    #[error("{} contains no records.", .0.display())]
+    EmptyInput(PathBuf),
+    /// An option out of range, or options which can't be used together.
+    #[error("{0}")]
+    InvalidOption(String),
+    /// A malformed TSV, bedGraph or BED file.
+    #[error("{0}")]
+    Parse(String),
+    /// A plot which couldn't be rendered.
+    #[error("{0}")]
+    Plot(String),
+    /// Intervals which can't be written to a bigWig.
+    #[error("{0}")]
+    BigWig(String),
+}
+
+/// A result with a [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// The exit code of the CLI for this kind of error. 2 is also used
+    /// by clap for command line errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidOption(_) => 2,
+            Error::Io(_) => 3,
+            Error::Fasta(_) => 4,
+            Error::Database(_) => 5,
+            Error::InvalidMotif(_) => 6,
+            Error::EmptyInput(_) => 7,
+            Error::Parse(_) => 8,
+            Error::Plot(_) => 9,
+            Error::BigWig(_) => 10,
+        }
+    }
+}
+
+/// Sequences are read as text to search them.
+impl From<std::str::Utf8Error> for Error {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Error::Fasta(format!("A sequence is not valid text: {}", e))
+    }
+}
+
+/// CSV is read from the TSV, bedGraph or BED input of `tidk plot`.
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+/// The database is fetched with `tidk build`.
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Database(format!("Could not fetch the database: {}", e))
+    }
+}
+
+/// Check a telomeric repeat is made of A, C, G and T, in either case.
+pub fn check_motif(motif: &str) -> Result<()> {
+    if motif.is_empty()
+        || !motif
+            .bytes()
+            .all(|base| matches!(base.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T'))
+    {
+        return Err(Error::InvalidMotif(motif.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_motif() {
+        assert!(check_motif("TTAGGG").is_ok());
+        assert!(check_motif("ttaggg").is_ok());
+        for motif in ["", "TTAGGN", "TTA GGG"] {
+            let error = check_motif(motif).unwrap_err();
+            assert!(matches!(error, Error::InvalidMotif(_)));
+            assert_eq!(error.exit_code(), 6);
+        }
+    }
+}
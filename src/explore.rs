@@ -1,14 +1,11 @@
-use crate::{utils, SubCommand};
-use anyhow::bail;
-use anyhow::Result;
-use bio::io::fasta;
+use crate::{input, utils, Error, Result, SubCommand};
 use itertools::Itertools;
 use rayon::prelude::*;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::PathBuf;
 use std::str;
-use std::sync::mpsc::channel;
 
 // when distance == 1, we get lower estimate of telomeric repeat number
 // than if we use distance == 0.1
@@ -16,118 +13,272 @@ use std::sync::mpsc::channel;
 
 static REPEAT_PERIOD_THRESHOLD: usize = 3;
 
-/// The function called from `tidk explore`. It takes the [`clap::Argmatches`]
-/// from the user and also a [`SubCommand`].
-pub fn explore(matches: &clap::ArgMatches, sc: SubCommand) -> Result<()> {
-    // parse arguments from main
-    let input_fasta = matches
-        .get_one::<PathBuf>("fasta")
-        .expect("errored by clap");
-    let length = *matches.get_one::<usize>("length").expect("errored by clap");
-
-    // if length is not set, these are the lengths (and length itself is set to zero)
-    let minimum = *matches
-        .get_one::<usize>("minimum")
-        .expect("errored by clap");
-    let maximum = *matches
-        .get_one::<usize>("maximum")
-        .expect("errored by clap");
-
-    let threshold = *matches
-        .get_one::<i32>("threshold")
-        .expect("errored by clap");
-
-    let dist_from_chromosome_end = *matches.get_one::<f64>("distance").expect("errored by clap");
+/// The options of `tidk explore`. Build with [`ExploreConfig::new()`],
+/// then set any options which aren't the defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExploreConfig {
+    /// The input FASTA or FASTQ, optionally gzipped.
+    pub fasta: PathBuf,
+    /// For FASTQ, mask bases below this Phred quality with N.
+    pub min_quality: Option<u8>,
+    /// The shortest repeat unit explored...
+    pub minimum: usize,
+    /// ...and the longest.
+    pub maximum: usize,
+    /// Runs of a unit are only counted with more copies than this.
+    pub threshold: usize,
+    /// The distance from each chromosome end searched, as a proportion
+    /// of its length, up to 0.5.
+    pub distance: f64,
+    /// Explore the ends of unassembled reads instead.
+    pub reads: bool,
+    /// With `reads`, the bases searched at each read end.
+    pub read_end: usize,
+    pub verbose: bool,
+    /// Write a log file.
+    pub log: bool,
+}
 
-    if dist_from_chromosome_end > 0.5 {
-        bail!("Distance from chromosome end as a proportion can't be more than 0.5.")
+impl ExploreConfig {
+    /// The default options, exploring `fasta` for units of length 5 to 12.
+    pub fn new(fasta: impl Into<PathBuf>) -> Self {
+        Self {
+            fasta: fasta.into(),
+            min_quality: None,
+            minimum: 5,
+            maximum: 12,
+            threshold: 100,
+            distance: 0.01,
+            reads: false,
+            read_end: 2000,
+            verbose: false,
+            log: false,
+        }
     }
 
-    let verbose = matches.get_flag("verbose");
+    /// Explore units of a single length.
+    pub fn length(self, length: usize) -> Self {
+        self.lengths(length, length)
+    }
 
-    // to report the telomeres...
-    let mut output_vec: Vec<RepeatPositions> = Vec::new();
-    // i.e. if you chose a length, as opposed to a minmum/maximum
-    if length > 0 {
-        eprintln!(
-            "[+]\tExploring genome for potential telomeric repeats of length: {}",
-            length
-        );
-        let reader = fasta::Reader::from_file(input_fasta)?;
+    /// Explore units from `minimum` to `maximum` long.
+    pub fn lengths(mut self, minimum: usize, maximum: usize) -> Self {
+        self.minimum = minimum;
+        self.maximum = maximum;
+        self
+    }
 
-        // try parallelising
-        let (sender, receiver) = channel();
+    pub fn min_quality(mut self, min_quality: Option<u8>) -> Self {
+        self.min_quality = min_quality;
+        self
+    }
 
-        reader
-            .records()
-            .par_bridge()
-            .for_each_with(sender, |s, record| {
-                let record = record.expect("[-]\tError during fasta record parsing.");
-                let id = record.id().to_owned();
-                let seq_len = record.seq().len();
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
 
-                let sequences = split_seq_by_distance(record, dist_from_chromosome_end, seq_len);
+    pub fn distance(mut self, distance: f64) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn reads(mut self, reads: bool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    pub fn read_end(mut self, read_end: usize) -> Self {
+        self.read_end = read_end;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
 
-                for sequence in sequences {
-                    let indexes = chunk_fasta(sequence, length, verbose, id.clone());
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+}
+
+/// A candidate telomeric repeat found in a genome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatEstimate {
+    /// The canonical repeat unit, see [`utils::lex_min()`].
+    pub unit: String,
+    /// The runs of the unit longer than the threshold.
+    pub runs: usize,
+}
+
+/// The candidate telomeric repeats found by `tidk explore`.
+#[derive(Debug)]
+pub enum Exploration {
+    /// From chromosome ends, most runs first.
+    Genome {
+        threshold: usize,
+        estimates: Vec<RepeatEstimate>,
+    },
+    /// From read ends, see [`ReadUnitCounts::estimates()`].
+    Reads(ReadUnitCounts),
+}
 
-                    if let Some(r) =
-                        calculate_indexes(indexes, length, verbose, id.clone(), threshold as usize)
-                    {
-                        s.send(r).expect("Did not send!");
-                    }
+impl Exploration {
+    /// Write the candidates as a TSV.
+    pub fn write_tsv<W: Write>(&self, file: &mut W) -> Result<()> {
+        match self {
+            Exploration::Genome {
+                threshold,
+                estimates,
+            } => {
+                // this is not technically a count - it's a count of runs > threshold
+                writeln!(
+                    file,
+                    "canonical_repeat_unit\tcount_repeat_runs_gt_{}",
+                    threshold
+                )?;
+                for estimate in estimates {
+                    writeln!(file, "{}\t{}", estimate.unit, estimate.runs)?;
                 }
-            });
+            }
+            Exploration::Reads(counts) => {
+                writeln!(
+                    file,
+                    "canonical_repeat_unit\tread_ends\tcopies\tinterior_copies\tenrichment"
+                )?;
+                for (unit, count) in counts.estimates() {
+                    writeln!(
+                        file,
+                        "{}\t{}\t{}\t{}\t{}",
+                        unit,
+                        count.read_ends,
+                        count.copies,
+                        count.interior_copies,
+                        counts
+                            .enrichment(&count)
+                            .map(|e| format!("{:.2}", e))
+                            .unwrap_or_default()
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
 
-        // this bit is a little chaotic
-        // collect output into a vector
-        let mut output = receiver.into_iter().collect();
+/// The function called from `tidk explore`, returning the candidate
+/// telomeric repeats.
+pub fn explore(config: &ExploreConfig) -> Result<Exploration> {
+    let input_fasta = &config.fasta;
+    let (minimum, maximum) = (config.minimum, config.maximum);
+    let threshold = config.threshold;
+    let dist_from_chromosome_end = config.distance;
 
-        output_vec.append(&mut output);
+    if dist_from_chromosome_end > 0.5 {
+        return Err(Error::InvalidOption(
+            "Distance from chromosome end as a proportion can't be more than 0.5.".to_string(),
+        ));
+    }
+    if minimum > maximum {
+        return Err(Error::InvalidOption(
+            "The minimum length can't be more than the maximum.".to_string(),
+        ));
+    }
+
+    let verbose = config.verbose;
+    let min_quality = config.min_quality;
+
+    // unassembled reads are explored by their ends instead
+    if config.reads {
+        let read_end = config.read_end;
+        let lengths = minimum..=maximum;
+        eprintln!(
+            "[+]\tExploring the ends of reads for periodic k-mers of length {} to {}",
+            lengths.start(),
+            lengths.end()
+        );
+        let (_, reader) = input::read_sequences(input_fasta, min_quality)?;
+        let counts = reader
+            .par_bridge()
+            .map(|record| {
+                record.map(|record| {
+                    Some(count_read_units(
+                        &record.seq,
+                        lengths.clone(),
+                        read_end,
+                        threshold,
+                    ))
+                })
+            })
+            .try_reduce(
+                || None,
+                |a, b| {
+                    Ok(match (a, b) {
+                        (Some(a), Some(b)) => Some(a.merge(b)),
+                        (a, b) => a.or(b),
+                    })
+                },
+            )?
+            .ok_or_else(|| Error::EmptyInput(input_fasta.clone()))?;
+        eprintln!("[+]\tFinished searching reads");
+
+        // optional log file
+        if config.log {
+            SubCommand::Explore(config).log()?;
+        }
+        return Ok(Exploration::Reads(counts));
+    }
+
+    // to report the telomeres...
+    let mut output_vec: Vec<RepeatPositions> = Vec::new();
+    if minimum == maximum {
+        eprintln!(
+            "[+]\tExploring genome for potential telomeric repeats of length: {}",
+            minimum
+        );
     } else {
         // if a range was chosen.
         eprintln!(
             "[+]\tExploring genome for potential telomeric repeats between lengths {} and {}.",
             minimum, maximum
         );
-        for length in minimum..maximum + 1 {
+    }
+    for length in minimum..maximum + 1 {
+        if minimum != maximum {
             eprintln!("[+]\t\tFinding telomeric repeat length: {}", length);
+        }
 
-            // have to call reader in the loop, as otherwise `reader` doesn't live long enough.
-            // I expect it's not an expensive call anyway.
-            let reader = fasta::Reader::from_file(input_fasta)?;
+        // have to call reader in the loop, as otherwise `reader` doesn't live long enough.
+        // I expect it's not an expensive call anyway.
+        let (_, reader) = input::read_sequences(input_fasta, min_quality)?;
 
-            // try parallelising
-            let (sender, receiver) = channel();
-            reader
-                .records()
-                .par_bridge()
-                .for_each_with(sender, |s, record| {
-                    let record = record.expect("[-]\tError during fasta record parsing.");
-                    let id = record.id().to_owned();
-                    let seq_len = record.seq().len();
+        // try parallelising, stopping at the first record which can't be read
+        let output = reader
+            .par_bridge()
+            .map(|record| -> Result<Vec<RepeatPositions>> {
+                let record = record?;
+                let id = record.id.clone();
+                let seq_len = record.seq.len();
 
-                    let sequences =
-                        split_seq_by_distance(record, dist_from_chromosome_end, seq_len);
+                let sequences = split_seq_by_distance(record, dist_from_chromosome_end, seq_len);
 
-                    for sequence in sequences {
+                Ok(sequences
+                    .into_iter()
+                    .filter_map(|sequence| {
                         let indexes = chunk_fasta(sequence, length, verbose, id.clone());
-
-                        if let Some(r) = calculate_indexes(
-                            indexes,
-                            length,
-                            verbose,
-                            id.clone(),
-                            threshold as usize,
-                        ) {
-                            s.send(r).expect("Did not send!");
-                        }
-                    }
-                });
-            let mut output = receiver.iter().collect();
-
-            output_vec.append(&mut output);
+                        calculate_indexes(indexes, length, verbose, id.clone(), threshold)
+                    })
+                    .collect())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // one entry per record
+        if output.is_empty() {
+            return Err(Error::EmptyInput(input_fasta.clone()));
         }
+
+        output_vec.extend(output.into_iter().flatten());
     }
     eprintln!("[+]\tFinished searching genome");
     eprintln!("[+]\tGenerating output");
@@ -137,30 +288,184 @@ pub fn explore(matches: &clap::ArgMatches, sc: SubCommand) -> Result<()> {
         repeat_postitions.add(&mut el.0);
     }
 
-    // print likely telomeric repeat
+    // likely telomeric repeats
     // costly calculation if threshold is too low.
-    let est = get_telomeric_repeat_estimates(&mut repeat_postitions)?;
+    let estimates = get_telomeric_repeat_estimates(&mut repeat_postitions)?
+        .into_iter()
+        .map(|(unit, runs)| RepeatEstimate {
+            unit,
+            runs: runs as usize,
+        })
+        .collect();
 
-    // this is not technically a count - it's a count of runs > threshold
-    println!("canonical_repeat_unit\tcount_repeat_runs_gt_{}", threshold);
-    for (cru, count) in est {
-        println!("{}\t{}", cru, count);
+    // optional log file
+    if config.log {
+        SubCommand::Explore(config).log()?;
     }
 
-    // optional log file
-    sc.log(matches)?;
+    Ok(Exploration::Genome {
+        threshold,
+        estimates,
+    })
+}
 
-    Ok(())
+/// The abundance of a repeat unit in reads.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnitCount {
+    /// The read ends with a run of the unit.
+    pub read_ends: usize,
+    /// Copies of the unit in runs at read ends.
+    pub copies: usize,
+    /// Copies of the unit in runs in the middle of reads.
+    pub interior_copies: usize,
+}
+
+/// Periodic k-mers counted at the ends, and in the middle, of reads.
+#[derive(Debug, Default)]
+pub struct ReadUnitCounts {
+    units: HashMap<String, UnitCount>,
+    /// The bases searched at read ends...
+    end_bases: usize,
+    /// ...and in the middle of reads.
+    interior_bases: usize,
+}
+
+impl ReadUnitCounts {
+    fn merge(mut self, other: Self) -> Self {
+        for (unit, count) in other.units {
+            let total = self.units.entry(unit).or_default();
+            total.read_ends += count.read_ends;
+            total.copies += count.copies;
+            total.interior_copies += count.interior_copies;
+        }
+        self.end_bases += other.end_bases;
+        self.interior_bases += other.interior_bases;
+        self
+    }
+
+    /// How much denser the unit is at read ends than in the middle of
+    /// reads, per base searched. One copy is added to each, so units
+    /// missing from the middle of reads still have an enrichment.
+    pub fn enrichment(&self, count: &UnitCount) -> Option<f64> {
+        if self.end_bases == 0 || self.interior_bases == 0 {
+            return None;
+        }
+        let end = (count.copies + 1) as f64 / self.end_bases as f64;
+        let interior = (count.interior_copies + 1) as f64 / self.interior_bases as f64;
+        Some(end / interior)
+    }
+
+    /// The units, most widespread at read ends first, without the short
+    /// period repeats which are unlikely to be telomeric.
+    pub fn estimates(&self) -> Vec<(String, UnitCount)> {
+        let mut estimates: Vec<(String, UnitCount)> = self
+            .units
+            .iter()
+            .filter(|(unit, count)| {
+                count.read_ends > 0 && check_repeats(unit) > REPEAT_PERIOD_THRESHOLD
+            })
+            .map(|(unit, count)| (unit.clone(), *count))
+            .collect();
+        estimates.sort_by(|a, b| {
+            (b.1.read_ends, b.1.copies)
+                .cmp(&(a.1.read_ends, a.1.copies))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        estimates
+    }
+}
+
+/// Count the periodic k-mers at the ends of a read, in the first and
+/// last `read_end` bases, and in as many bases from its middle as a
+/// control. Reads too short for both ends are searched as one end,
+/// and only reads three times as long have a middle.
+pub fn count_read_units(
+    read: &[u8],
+    lengths: std::ops::RangeInclusive<usize>,
+    read_end: usize,
+    threshold: usize,
+) -> ReadUnitCounts {
+    let read = read.to_ascii_uppercase();
+    let mut counts = ReadUnitCounts::default();
+    let ends: Vec<&[u8]> = if read.len() < 2 * read_end {
+        vec![&read]
+    } else {
+        vec![&read[..read_end], &read[read.len() - read_end..]]
+    };
+    for end in ends {
+        counts.end_bases += end.len();
+        let mut units: HashMap<String, usize> = HashMap::new();
+        for length in lengths.clone() {
+            for (unit, copies) in periodic_runs(end, length, threshold) {
+                *units.entry(unit).or_default() += copies;
+            }
+        }
+        for (unit, copies) in units {
+            let count = counts.units.entry(unit).or_default();
+            count.read_ends += 1;
+            count.copies += copies;
+        }
+    }
+    if read.len() >= 3 * read_end {
+        let start = (read.len() - read_end) / 2;
+        let interior = &read[start..start + read_end];
+        counts.interior_bases += interior.len();
+        for length in lengths {
+            for (unit, copies) in periodic_runs(interior, length, threshold) {
+                counts.units.entry(unit).or_default().interior_copies += copies;
+            }
+        }
+    }
+    counts
+}
+
+/// Find the runs of a unit of `length` repeated in tandem more than
+/// `threshold` times, as the canonical unit (see [`utils::lex_min()`])
+/// and its copies. Units which are themselves repeats of a shorter
+/// unit are left to that length.
+fn periodic_runs(sequence: &[u8], length: usize, threshold: usize) -> Vec<(String, usize)> {
+    let mut runs = Vec::new();
+    if length == 0 {
+        return runs;
+    }
+    let mut position = 0;
+    while position + length < sequence.len() {
+        if sequence[position] != sequence[position + length] {
+            position += 1;
+            continue;
+        }
+        // extend while each base matches the one a unit along
+        let start = position;
+        while position + length < sequence.len()
+            && sequence[position] == sequence[position + length]
+        {
+            position += 1;
+        }
+        let copies = (position + length - start) / length;
+        let unit = &sequence[start..start + length];
+        if copies > threshold && !unit.contains(&b'N') && is_primitive(unit) {
+            let unit = str::from_utf8(unit).unwrap_or_default();
+            runs.push((utils::lex_min(unit), copies));
+        }
+    }
+    runs
+}
+
+/// Is the unit not itself a tandem repeat of a shorter unit?
+fn is_primitive(unit: &[u8]) -> bool {
+    (1..unit.len())
+        .filter(|period| unit.len().is_multiple_of(*period))
+        .all(|period| unit.chunks(period).any(|chunk| chunk != &unit[..period]))
 }
 
 pub fn split_seq_by_distance(
-    sequence: bio::io::fasta::Record,
+    sequence: input::Sequence,
     dist_from_chromosome_end: f64,
     seq_len: usize,
 ) -> [Vec<u8>; 2] {
     let dist = (seq_len as f64 * dist_from_chromosome_end).ceil() as usize;
-    let filtered_sequence1 = sequence.seq()[0..dist].to_vec();
-    let filtered_sequence2 = sequence.seq()[(seq_len - dist)..].to_vec();
+    let filtered_sequence1 = sequence.seq[0..dist].to_vec();
+    let filtered_sequence2 = sequence.seq[(seq_len - dist)..].to_vec();
     [filtered_sequence1, filtered_sequence2]
 }
 
@@ -215,19 +520,19 @@ fn chunk_fasta(
             if is_first_consecutive {
                 indexes.push(ChunkedFasta {
                     position: pos,
-                    sequence: str::from_utf8(a).unwrap().to_uppercase(),
+                    sequence: String::from_utf8_lossy(a).to_uppercase(),
                 });
                 pos += chunk_length;
                 indexes.push(ChunkedFasta {
                     position: pos,
-                    sequence: str::from_utf8(a).unwrap().to_uppercase(),
+                    sequence: String::from_utf8_lossy(a).to_uppercase(),
                 });
                 is_first_consecutive = false;
             } else {
                 pos += chunk_length;
                 indexes.push(ChunkedFasta {
                     position: pos,
-                    sequence: str::from_utf8(a).unwrap().to_uppercase(),
+                    sequence: String::from_utf8_lossy(a).to_uppercase(),
                 });
             }
         } else if a != b {
@@ -408,11 +713,9 @@ fn get_telomeric_repeat_estimates(
             let first = &telomeric_repeats_i[comb[0]];
             let second = &telomeric_repeats_i[comb[1]];
 
-            let (is_rotation, potential_sequence) = test_repeats(first, second);
             // if the combination is a string rotation (or its reverse complement)
             // then combine
-            if is_rotation {
-                let sequence = potential_sequence.unwrap();
+            if let (true, Some(sequence)) = test_repeats(first, second) {
                 // if comb[0] || comb[1] not in tracker...
                 // as we already added the contents of the tracked telomeric repeats
                 // we do not want to count them again.
@@ -431,7 +734,7 @@ fn get_telomeric_repeat_estimates(
     }
 
     let mut count_vec: Vec<_> = map.into_iter().collect();
-    count_vec.sort_by(|a, b| b.1.cmp(&a.1));
+    count_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
     filter_count_vec(&mut count_vec)?;
 
     Ok(count_vec)
@@ -448,7 +751,7 @@ fn check_repeats(s: &str) -> usize {
         delays.retain(|_, iter| iter.next() == Some(c));
         delays.insert(i + 1, s.chars());
     }
-    delays.into_keys().next().unwrap()
+    delays.into_keys().next().unwrap_or(0)
 }
 
 /// A function to filter the final count vec of certain kinds of
@@ -507,7 +810,11 @@ mod tests {
     const DIST_FROM_CHROM_END: f64 = 0.5;
 
     fn split_by_dist(genome: &str) -> [Vec<u8>; 2] {
-        let record = bio::io::fasta::Record::with_attrs("id1", None, genome.as_bytes());
+        let record = crate::input::Sequence {
+            id: "id1".to_string(),
+            seq: genome.as_bytes().to_vec(),
+            qual: None,
+        };
         split_seq_by_distance(record, DIST_FROM_CHROM_END, genome.len())
     }
 
@@ -631,4 +938,55 @@ mod tests {
         let res = get_telomeric_repeat_estimates(&mut indices).unwrap();
         assert_eq!(res, vec![("AACCT".to_string(), 4)]);
     }
+
+    #[test]
+    fn test_periodic_runs() {
+        let read = b"GATCGATTACATTAGGGTTAGGGTTAGGGTTAGGGTTAGGCATCAAAAAAAAAAAAAGAT";
+        let runs = periodic_runs(read, 6, 2);
+        assert_eq!(runs, vec![("AACCCT".to_string(), 4)]);
+        // the poly-A is a run of period 1, not 6
+        assert!(periodic_runs(read, 6, 0)
+            .iter()
+            .all(|(unit, _)| unit != "AAAAAA"));
+        assert_eq!(periodic_runs(read, 1, 5), vec![("A".to_string(), 13)]);
+        // whichever rotation the run starts with
+        assert_eq!(
+            periodic_runs(b"GGTTAGGGTTAGGGTTAG", 6, 1),
+            vec![("AACCCT".to_string(), 3)]
+        );
+        assert!(!is_primitive(b"TTAGGGTTAGGG"));
+    }
+
+    #[test]
+    fn test_count_read_units() {
+        let middle = "GATTACA".repeat(30);
+        let read = format!("{}{}{}", "CCCTAA".repeat(20), middle, "TTAGGG".repeat(20));
+        let counts = count_read_units(read.as_bytes(), 5..=7, 100, 3);
+        let estimates = counts.estimates();
+        assert_eq!(estimates[0].0, "AACCCT");
+        assert_eq!(estimates[0].1.read_ends, 2);
+        // 16 whole copies in the 100 bases at each end
+        assert_eq!(estimates[0].1.copies, 32);
+        // GATTACA is only in the middle
+        let gattaca = &counts.units[&utils::lex_min("GATTACA")];
+        assert_eq!((gattaca.read_ends, gattaca.interior_copies), (0, 14));
+        assert!(counts.enrichment(&estimates[0].1).unwrap() > 1.0);
+    }
+
+    #[test]
+    fn test_explore_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        // a record without its separator and quality lines
+        let malformed = dir.path().join("malformed.fq");
+        std::fs::write(&malformed, "@read1\nTTAGGGTTAGGG\n").unwrap();
+        let empty = dir.path().join("empty.fa");
+        std::fs::write(&empty, "").unwrap();
+
+        for reads in [false, true] {
+            let config = ExploreConfig::new(&malformed).reads(reads);
+            assert!(matches!(explore(&config), Err(Error::Fasta(_))));
+            let config = ExploreConfig::new(&empty).reads(reads);
+            assert!(matches!(explore(&config), Err(Error::EmptyInput(_))));
+        }
+    }
 }
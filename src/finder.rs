@@ -1,43 +1,142 @@
-use crate::{clades, utils, SubCommand};
-use anyhow::{Context, Result};
-use bio::io::fasta;
+use crate::{
+    bigwig::CountTracks,
+    clades,
+    input::{self, Sequence},
+    windows::{self, Extension, SequenceSummary, SoftMask, WindowCounts, WindowsSummary},
+    Error, Result, SubCommand,
+};
 use std::fs::{create_dir_all, File};
 use std::io::LineWriter;
 use std::io::Write;
 use std::path::PathBuf;
-use std::process;
-use std::str;
+
+/// The options of `tidk find`. Build with [`FindConfig::new()`], then
+/// set any options which aren't the defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindConfig {
+    /// The input FASTA or FASTQ, optionally gzipped.
+    pub fasta: PathBuf,
+    /// The clade, whose telomeric repeats are searched for.
+    pub clade: String,
+    /// The output file name, without extension.
+    pub output: String,
+    /// The directory the output is written to.
+    pub dir: PathBuf,
+    /// For FASTQ, mask bases below this Phred quality with N.
+    pub min_quality: Option<u8>,
+    pub window: usize,
+    /// Tiles the windows if `None`.
+    pub step: Option<usize>,
+    /// The TSV, or bigWig tracks (bedGraph isn't written).
+    pub extension: Extension,
+    pub soft_mask: SoftMask,
+    /// Write densities, not counts, to bigWig.
+    pub normalise: bool,
+    /// Separate forward, reverse and total bigWig tracks.
+    pub stranded: bool,
+    /// Write a log file.
+    pub log: bool,
+}
+
+impl FindConfig {
+    /// The default options, searching `fasta` for the telomeric repeats
+    /// of `clade` and writing to `dir`/`output`.
+    pub fn new(
+        fasta: impl Into<PathBuf>,
+        clade: &str,
+        output: &str,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            fasta: fasta.into(),
+            clade: clade.to_string(),
+            output: output.to_string(),
+            dir: dir.into(),
+            min_quality: None,
+            window: 10000,
+            step: None,
+            extension: Extension::Tsv,
+            soft_mask: SoftMask::Include,
+            normalise: false,
+            stranded: false,
+            log: false,
+        }
+    }
+
+    pub fn min_quality(mut self, min_quality: Option<u8>) -> Self {
+        self.min_quality = min_quality;
+        self
+    }
+
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn step(mut self, step: Option<usize>) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    pub fn soft_mask(mut self, soft_mask: SoftMask) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
+    pub fn normalise(mut self, normalise: bool) -> Self {
+        self.normalise = normalise;
+        self
+    }
+
+    pub fn stranded(mut self, stranded: bool) -> Self {
+        self.stranded = stranded;
+        self
+    }
+
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Count the clade's repeats in the windows of each sequence, as
+    /// they're read, rather than writing them out.
+    pub fn window_counts(&self) -> Result<WindowCounts> {
+        let clade_info = clades::return_telomere_sequence(self.clade.clone())?;
+        let (_, sequences) = input::read_sequences(&self.fasta, self.min_quality)?;
+        Ok(WindowCounts::new(
+            sequences,
+            clade_info.seq.get_inner().to_vec(),
+            self.window,
+            self.step.unwrap_or(self.window),
+            self.soft_mask,
+        ))
+    }
+}
 
 /// The entry point for `tidk find`.
 ///
 /// Finder uses the clade specific telomere sequence and queries against the genome.
-pub fn finder(matches: &clap::ArgMatches, sc: SubCommand) -> Result<()> {
-    // print table of telomeric sequences
-    if matches.get_flag("print") {
-        clades::print_table()?;
-        process::exit(1);
+pub fn finder(config: &FindConfig) -> Result<WindowsSummary> {
+    if config.extension == Extension::BedGraph {
+        return Err(Error::InvalidOption(
+            "`tidk find` writes TSV or bigWig, not bedGraph.".to_string(),
+        ));
     }
+    let (_, sequences) = input::read_sequences(&config.fasta, config.min_quality)?;
 
-    let input_fasta: PathBuf = matches
-        .get_one::<PathBuf>("fasta")
-        .expect("errored by clap")
-        .clone();
-    let reader = fasta::Reader::from_file(input_fasta)?;
-
-    let clade = matches
-        .get_one::<String>("clade")
-        .expect("errored by clap")
-        .clone();
-
-    let clade_info = clades::return_telomere_sequence(clade)?;
+    let clade_info = clades::return_telomere_sequence(config.clade.clone())?;
 
     if clade_info.length == 1 {
         eprintln!(
             "[+]\tSearching genome for a single telomeric repeat: {}",
-            clade_info
-                .seq
-                .get(0)
-                .context("Could not get the first element of `seq`.")?
+            clade_info.seq.get(0).ok_or_else(|| Error::Database(
+                "Could not get the first element of `seq`.".to_string()
+            ))?
         );
     } else if clade_info.length > 1 {
         eprintln!(
@@ -47,138 +146,147 @@ pub fn finder(matches: &clap::ArgMatches, sc: SubCommand) -> Result<()> {
         for telomeric_repeat in 0..clade_info.length {
             eprintln!(
                 "[+]\t\t{}",
-                clade_info.seq.get(telomeric_repeat).context(format!(
-                    "Could not get the {} element of `seq`.",
-                    telomeric_repeat
-                ))?
+                clade_info.seq.get(telomeric_repeat).ok_or_else(|| {
+                    Error::Database(format!(
+                        "Could not get the {} element of `seq`.",
+                        telomeric_repeat
+                    ))
+                })?
             );
         }
     }
 
-    let window_size: usize = *matches.get_one::<usize>("window").expect("errored by clap");
-    let outdir = matches.get_one::<PathBuf>("dir").expect("errored by clap");
-    let output = matches
-        .get_one::<PathBuf>("output")
-        .expect("errored by clap");
+    // tiles, unless a step is given
+    let step = config.step.unwrap_or(config.window);
+    let outdir = &config.dir;
+    let output = &config.output;
 
     // create directory for output
     create_dir_all(outdir)?;
-
-    // create file
-    let file_name = format!(
-        "{}/{}{}",
-        outdir.display(),
-        output.display(),
-        "_telomeric_repeat_windows.tsv"
-    );
-    let finder_file = File::create(file_name)?;
-    let mut finder_file = LineWriter::new(finder_file);
-    // add headers
-    writeln!(
-        finder_file,
-        "id\twindow\tforward_repeat_number\treverse_repeat_number\ttelomeric_repeat"
-    )?;
+    let mut summary = WindowsSummary::default();
 
     // extract the string from TelomereSeq struct
     // dereference here because of Box<T>
     let telomeric_repeat = clade_info.seq.get_inner();
 
-    // iterate over the fasta records
-    for result in reader.records() {
+    // create file, or bigWig tracks for each telomeric repeat
+    let mut bigwigs = Vec::new();
+    let finder_file: Box<dyn Write> = if config.extension == Extension::BigWig {
+        for repeat in telomeric_repeat {
+            // name the files by repeat if there is more than one
+            let prefix = if telomeric_repeat.len() > 1 {
+                format!(
+                    "{}/{}_{}_telomeric_repeat_windows",
+                    outdir.display(),
+                    output,
+                    repeat
+                )
+            } else {
+                format!("{}/{}_telomeric_repeat_windows", outdir.display(), output)
+            };
+            summary
+                .files
+                .extend(CountTracks::file_names(&prefix, config.stranded));
+            bigwigs.push(CountTracks::create(
+                &prefix,
+                config.stranded,
+                config.window,
+                step,
+                config.normalise,
+            )?);
+        }
+        Box::new(std::io::sink())
+    } else {
+        let file_name = outdir.join(format!("{}_telomeric_repeat_windows.tsv", output));
+        let mut file = File::create(&file_name)?;
+        // add headers
+        windows::write_tsv_header(&mut file)?;
+        summary.files.push(file_name);
+        Box::new(file)
+    };
+    let mut finder_file = LineWriter::new(finder_file);
+
+    // iterate over the fasta or fastq records
+    for result in sequences {
         let record = result?;
-        let id = record.id().to_owned();
+        let id = record.id.clone();
+        let length = record.seq.len();
 
         // fn window counter
-        write_window_counts(
+        let windows = write_window_counts(
             record,
             &mut finder_file,
-            clade_info.clone(),
             telomeric_repeat,
-            window_size,
-            id.clone(),
+            config.window,
+            step,
+            config.soft_mask,
+            &mut bigwigs,
         )?;
 
         eprintln!("[+]\tChromosome {} processed", id);
+        summary.sequences.push(SequenceSummary {
+            id,
+            length,
+            windows,
+        });
+    }
+    if summary.sequences.is_empty() {
+        return Err(Error::EmptyInput(config.fasta.clone()));
+    }
+    for bigwig in bigwigs {
+        bigwig.finish()?;
     }
     eprintln!("[+]\tFinished searching genome.");
 
     // optional log file
-    sc.log(matches)?;
+    if config.log {
+        SubCommand::Find(config).log()?;
+    }
 
-    Ok(())
+    Ok(summary)
 }
 
 /// Creates the window iterator and iterates over each iteration of the
-/// fasta file, writing on the fly.
+/// fasta file, writing on the fly. Returns the number of windows, per
+/// telomeric repeat.
 fn write_window_counts<T: std::io::Write>(
-    sequence: bio::io::fasta::Record,
+    sequence: Sequence,
     file: &mut LineWriter<T>,
-    clade_info: clades::TelomereSeq,
-    telomeric_repeat: &Vec<String>,
+    telomeric_repeat: &[String],
     window_size: usize,
-    id: String,
-) -> Result<()> {
+    step: usize,
+    soft_mask: SoftMask,
+    bigwigs: &mut [CountTracks],
+) -> Result<usize> {
+    let id = &sequence.id;
+    let mut window_number = 0;
     // needed as in some clades there is more than one telomeric repeat sequence
-    let mut telomeric_repeat_index = 0;
-    loop {
-        // break this loop if we reach the end of &[&str] of telomeric repeats
-        if clade_info.length == telomeric_repeat_index {
-            break;
+    for (telomeric_repeat_index, forward_telomeric_seq) in telomeric_repeat.iter().enumerate() {
+        let mut bigwig = bigwigs.get_mut(telomeric_repeat_index);
+        if let Some(bigwig) = bigwig.as_mut() {
+            bigwig.add_chromosome(id, sequence.seq.len())?;
         }
 
-        // get forward and reverse sequences, and length
-        // to remove overlapping matches.
-        let forward_telomeric_seq =
-            telomeric_repeat
-                .get(telomeric_repeat_index)
-                .context(format!(
-                    "Could not get the telomeric repeat with index: {}.",
-                    telomeric_repeat_index
-                ))?;
-        let reverse_telomeric_seq = utils::reverse_complement(&forward_telomeric_seq);
-        let current_telomeric_length = forward_telomeric_seq.len();
-
-        // create the iterator in each loop iteration isnt costly is it?
-        let windows = sequence.seq().chunks(window_size);
-        // keep track of the window size
-
-        let mut end = window_size;
         // iterate over windows
-        for (i, window) in windows.enumerate() {
-            // make window uppercase
-            let windows_upper = str::from_utf8(window)?.to_uppercase();
-            // for each window, find the motifs in this
-            let forward_motif = utils::find_motifs(&forward_telomeric_seq, &windows_upper);
-            let reverse_motif = utils::find_motifs(&reverse_telomeric_seq, &windows_upper);
-
-            // remove overlapping matches
-            // not sure this is necessary, but thought it might be...
-            let forward_motif_noverlap =
-                utils::remove_overlapping_indexes(forward_motif, current_telomeric_length);
-            let reverse_motif_noverlap =
-                utils::remove_overlapping_indexes(reverse_motif, current_telomeric_length);
-
-            // the number of matches for forward/reverse
-            let forward_repeat_number = forward_motif_noverlap.len();
-            let reverse_repeat_number = reverse_motif_noverlap.len();
-
-            if i != 0 {
-                end += window_size;
-            }
-            if end > sequence.seq().len() {
-                end = sequence.seq().len();
-            }
+        let windows = windows::count_windows(
+            id,
+            &sequence.seq,
+            forward_telomeric_seq,
+            window_size,
+            step,
+            soft_mask,
+        )?;
+        window_number = windows.len();
+        for window in windows {
             // write to file
-            writeln!(
-                file,
-                "{}\t{}\t{}\t{}\t{}",
-                id, end, forward_repeat_number, reverse_repeat_number, forward_telomeric_seq
-            )?;
+            if let Some(bigwig) = bigwig.as_mut() {
+                bigwig.add(&window)?;
+            } else {
+                windows::write_tsv_row(file, &window)?;
+            }
         }
-        // go to the next telomeric repeat (if there is one)
-        telomeric_repeat_index += 1;
     }
-    Ok(())
+    Ok(window_number)
 }
 
 #[cfg(test)]
@@ -187,21 +295,33 @@ mod tests {
 
     use crate::clades::{Seq, TelomereSeq};
 
-    use super::write_window_counts;
+    use super::{write_window_counts, Sequence, SoftMask};
 
-    // a wrapper for making a bio::io::fasta record
-    fn make_record(id: &str, seq: &[u8]) -> bio::io::fasta::Record {
-        bio::io::fasta::Record::with_attrs(id, None, seq)
+    // a wrapper for making a sequence record
+    fn make_record(id: &str, seq: &[u8]) -> Sequence {
+        Sequence {
+            id: id.to_string(),
+            seq: seq.to_vec(),
+            qual: None,
+        }
     }
 
     // take a record, write to a vector (fake file), then read out of this the output.
-    fn calc_windows(rec: bio::io::fasta::Record, ts: TelomereSeq, ws: usize) -> String {
+    fn calc_windows(rec: Sequence, ts: TelomereSeq, ws: usize) -> String {
         let file = Vec::new();
         let mut lw = LineWriter::new(file);
-        let id = rec.id().to_owned();
 
         let telomeric_repeat = ts.seq.get_inner().clone();
-        write_window_counts(rec, &mut lw, ts, &telomeric_repeat, ws, id).unwrap();
+        write_window_counts(
+            rec,
+            &mut lw,
+            &telomeric_repeat,
+            ws,
+            ws,
+            SoftMask::Include,
+            &mut [],
+        )
+        .unwrap();
 
         // read file contents to new vec
         let mut out = Vec::new();
@@ -231,10 +351,19 @@ mod tests {
         let rows: Vec<&str> = windows_calculation.lines().collect();
 
         // three in first window
-        assert_eq!(rows[0], "test1\t20\t2\t0\tAAACCCT");
+        assert_eq!(
+            rows[0],
+            "test1\t0\t20\t20\t2\t0\tAAACCCT\t0\t0\t0.4500\t0.7000\t\t"
+        );
         // none in second
-        assert_eq!(rows[1], "test1\t40\t0\t0\tAAACCCT");
+        assert_eq!(
+            rows[1],
+            "test1\t20\t40\t20\t0\t0\tAAACCCT\t0\t0\t0.6500\t0.0000\t\t"
+        );
         // two in third
-        assert_eq!(rows[2], "test1\t57\t1\t0\tAAACCCT");
+        assert_eq!(
+            rows[2],
+            "test1\t40\t57\t17\t1\t0\tAAACCCT\t0\t0\t0.5294\t0.4118\t\t"
+        );
     }
 }
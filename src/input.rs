@@ -0,0 +1,167 @@
+use crate::{utils, Error, Result};
+use bio::io::{fasta, fastq};
+use flate2::read::MultiGzDecoder;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The first bytes of a gzip file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A sequence from a FASTA or FASTQ file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    pub id: String,
+    pub seq: Vec<u8>,
+    /// The Phred+33 qualities, for FASTQ.
+    pub qual: Option<Vec<u8>>,
+}
+
+impl Sequence {
+    /// Replace each base with a Phred quality below `min_quality` with an
+    /// N, so no repeat is matched across it. FASTA is left as it is.
+    pub fn mask_low_quality(&mut self, min_quality: u8) {
+        let Some(qual) = &self.qual else {
+            return;
+        };
+        for (base, quality) in self.seq.iter_mut().zip(qual) {
+            if quality.saturating_sub(33) < min_quality {
+                *base = b'N';
+            }
+        }
+    }
+}
+
+/// The formats of sequence file which can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Fasta,
+    Fastq,
+}
+
+/// The sequences of a file, in order. These can be sent between threads,
+/// e.g. with `par_bridge`.
+pub type Sequences = Box<dyn Iterator<Item = Result<Sequence>> + Send>;
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Fasta => write!(f, "FASTA"),
+            Format::Fastq => write!(f, "FASTQ"),
+        }
+    }
+}
+
+/// Open a file, decompressing it if it's gzipped.
+fn open(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::new(utils::open_file(path)?);
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Tell FASTA from FASTQ by the first byte. An empty file is read as
+/// FASTA, with no records.
+fn detect_format(reader: &mut dyn BufRead) -> Result<Format> {
+    match reader.fill_buf()?.first() {
+        Some(b'>') | None => Ok(Format::Fasta),
+        Some(b'@') => Ok(Format::Fastq),
+        Some(byte) => Err(Error::Fasta(format!(
+            "Expected FASTA ('>') or FASTQ ('@'), but the file starts with {:?}.",
+            *byte as char
+        ))),
+    }
+}
+
+/// Iterate over the sequences in a FASTA or FASTQ file, which may be
+/// gzipped. Both are detected from the file contents. Qualities are
+/// ignored, unless bases below `min_quality` are to be masked.
+pub fn read_sequences(path: &Path, min_quality: Option<u8>) -> Result<(Format, Sequences)> {
+    let mut reader = open(path)?;
+    let format = detect_format(&mut reader)?;
+    if format == Format::Fasta && min_quality.is_some() {
+        eprintln!(
+            "[-]\t{} is FASTA, which has no qualities to mask.",
+            path.display()
+        );
+    }
+    let sequences: Sequences = match format {
+        Format::Fasta => Box::new(fasta::Reader::from_bufread(reader).records().map(|record| {
+            let record =
+                record.map_err(|e| Error::Fasta(format!("Could not parse FASTA: {}", e)))?;
+            Ok(Sequence {
+                id: record.id().to_string(),
+                seq: record.seq().to_vec(),
+                qual: None,
+            })
+        })),
+        Format::Fastq => Box::new(fastq::Reader::from_bufread(reader).records().map(|record| {
+            let record =
+                record.map_err(|e| Error::Fasta(format!("Could not parse FASTQ: {}", e)))?;
+            Ok(Sequence {
+                id: record.id().to_string(),
+                seq: record.seq().to_vec(),
+                qual: Some(record.qual().to_vec()),
+            })
+        })),
+    };
+    let sequences = match min_quality {
+        Some(min_quality) => Box::new(sequences.map(move |sequence| {
+            let mut sequence = sequence?;
+            sequence.mask_low_quality(min_quality);
+            Ok(sequence)
+        })),
+        None => sequences,
+    };
+    Ok((format, sequences))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::fs::File;
+    use std::io::Write;
+
+    fn sequences(path: &Path) -> (Format, Vec<Sequence>) {
+        let (format, sequences) = read_sequences(path, None).unwrap();
+        (format, sequences.map(|s| s.unwrap()).collect())
+    }
+
+    #[test]
+    fn test_read_sequences() {
+        let dir = tempfile::tempdir().unwrap();
+        let fasta = dir.path().join("input.fa");
+        std::fs::write(
+            &fasta,
+            ">read1 a description\nTTAGG\nTTAGG\n>read2\nCCTAA\n",
+        )
+        .unwrap();
+        let fastq = dir.path().join("input.fq.gz");
+        let mut gz = GzEncoder::new(File::create(&fastq).unwrap(), Compression::default());
+        gz.write_all(b"@read1\nTTAGG\n+\nIIII#\n").unwrap();
+        gz.finish().unwrap();
+
+        let (format, records) = sequences(&fasta);
+        assert_eq!(format, Format::Fasta);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "read1");
+        assert_eq!(records[0].seq, b"TTAGGTTAGG");
+        assert_eq!(records[1].qual, None);
+
+        let (format, records) = sequences(&fastq);
+        assert_eq!(format, Format::Fastq);
+        assert_eq!(records[0].seq, b"TTAGG");
+        assert_eq!(records[0].qual.as_deref(), Some(&b"IIII#"[..]));
+
+        let other = dir.path().join("input.txt");
+        std::fs::write(&other, "TTAGG\n").unwrap();
+        assert!(read_sequences(&other, None).is_err());
+
+        // '#' is Phred 2, 'I' is 40
+        let (_, masked) = read_sequences(&fastq, Some(20)).unwrap();
+        let masked: Vec<Sequence> = masked.map(|s| s.unwrap()).collect();
+        assert_eq!(masked[0].seq, b"TTAGN");
+    }
+}
@@ -1,209 +1,230 @@
-use anyhow::Result;
 use chrono::Local;
 use clap::crate_version;
 use std::{io::Write, path::PathBuf};
 
+/// A bigWig writer for the window counts of `tidk search`
+/// and `tidk find`.
+pub mod bigwig;
 /// For building the database
 pub mod build;
+/// A C API to scan a FASTA for a telomeric repeat, see
+/// `include/tidk.h`.
+#[cfg(feature = "capi")]
+pub mod capi;
 /// A module where the clades are defined, and their
 /// respective telomeric repeats are enumerated.
 pub mod clades;
+/// Matching telomeric reads to contig ends, for
+/// `tidk reads --assembly`.
+pub mod ends;
+/// The errors of the library, and the exit codes of the CLI.
+pub mod error;
 /// The entry point for the `tidk explore` subcommand.
 pub mod explore;
 /// The entry point for the `tidk find` subcommand.
 pub mod finder;
+/// Reading FASTA or FASTQ, plain or gzipped.
+pub mod input;
 /// Functions to plot output from `tidk search` and
 /// `tidk find`.
 pub mod plot;
+/// Python bindings, built with maturin.
+#[cfg(feature = "python")]
+pub mod python;
+/// The entry point for the `tidk reads` subcommand.
+pub mod reads;
 /// The entry point for the `tidk search` subcommand.
 pub mod search;
 /// Module for utilities.
 pub mod utils;
-
-/// Three possible subcommands.
-pub enum SubCommand {
-    Find,
-    Explore,
-    Search,
+/// Counting telomeric repeats in windows along a sequence, for
+/// `tidk search` and `tidk find`.
+pub mod windows;
+
+pub use error::{Error, Result};
+
+/// The subcommands which can write a log, with the options they were
+/// run with.
+pub enum SubCommand<'a> {
+    Find(&'a finder::FindConfig),
+    Explore(&'a explore::ExploreConfig),
+    Search(&'a search::SearchConfig),
+    Reads(&'a reads::ReadsConfig),
 }
 
 /// A date format.
 const DATE_FORMAT_STR: &str = "%Y-%m-%d: %H:%M:%S";
 
-// this is not the optimal way to do this... but oh well.
+/// An optional value for the log, or `none`.
+fn log_option<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("none".to_string(), |value| value.to_string())
+}
+
 // add optional log file directory
-impl SubCommand {
+impl SubCommand<'_> {
     /// Make a log dependent on the subcommand that was run.
-    pub fn log(&self, matches: &clap::ArgMatches) -> Result<()> {
-        // only if log CLI arg is present
-        if matches.get_flag("log") {
-            match self {
-                SubCommand::Find => {
-                    let output = matches
-                        .get_one::<PathBuf>("output")
-                        .expect("errored by clap");
-                    let outdir = matches.get_one::<PathBuf>("dir").expect("errored by clap");
-                    let input_fasta = matches
-                        .get_one::<PathBuf>("fasta")
-                        .expect("errored by clap");
-                    let clade = matches.get_one::<String>("clade").expect("errored by clap");
-                    let clade_info = clades::return_telomere_sequence(clade.clone());
-                    let window_size = *matches.get_one::<usize>("window").expect("errored by clap");
-
-                    let file_name = format!(
-                        "{}/{}{}",
-                        outdir.display(),
-                        output.display(),
-                        "_telomeric_repeat_windows.csv"
-                    );
-
-                    let log_string = format!(
-                        r#"tidk version: {}
+    pub fn log(&self) -> Result<()> {
+        let (log_file_name, log_string) = match self {
+            SubCommand::Find(config) => {
+                let clade_info = clades::return_telomere_sequence(config.clade.clone())?;
+                let file_name = config.dir.join(format!(
+                    "{}_telomeric_repeat_windows.{}",
+                    config.output,
+                    config.extension.name()
+                ));
+
+                let log_string = format!(
+                    r#"tidk version: {}
 Log information for output file: {}
 Date: {}
 `tidk find` was run with the following parameters:
     Input fasta: {}
+    Minimum base quality: {}
     Window size: {}
+    Step: {}
     Clade chosen: {}
-    Telomeric repeats queried: {}"#,
-                        crate_version!(),
-                        file_name,
-                        Local::now().format(DATE_FORMAT_STR),
-                        input_fasta.display(),
-                        window_size,
-                        clade,
-                        clade_info?.seq.get_inner().join(", ")
-                    );
-
-                    // create file
-                    let log_file_name =
-                        format!("{}/{}{}", outdir.display(), output.display(), ".log");
-                    let log_file = std::fs::File::create(&log_file_name)?;
-                    let mut log_file = std::io::LineWriter::new(log_file);
-
-                    writeln!(log_file, "{}", log_string)?;
-
-                    Ok(eprintln!("[+]\tLog file written to: {}", log_file_name))
-                }
-                SubCommand::Explore => {
-                    let input_fasta = matches
-                        .get_one::<PathBuf>("fasta")
-                        .expect("errored by clap");
-                    let length = matches.get_one::<usize>("length");
-
-                    let minimum = matches.get_one::<usize>("minimum");
-                    let maximum = matches.get_one::<usize>("maximum");
-
-                    let threshold = matches.get_one::<i32>("threshold");
-
-                    let dist_from_chromosome_end = matches.get_one::<f64>("distance");
-
-                    let log_string = format!(
-                        r#"tidk version: {}
+    Telomeric repeats queried: {}
+    Output format: {}
+    Soft-masked repeats: {}
+    Densities written: {}
+    Stranded tracks: {}"#,
+                    crate_version!(),
+                    file_name.display(),
+                    Local::now().format(DATE_FORMAT_STR),
+                    config.fasta.display(),
+                    log_option(config.min_quality),
+                    config.window,
+                    log_option(config.step),
+                    config.clade,
+                    clade_info.seq.get_inner().join(", "),
+                    config.extension.name(),
+                    config.soft_mask.name(),
+                    config.normalise,
+                    config.stranded
+                );
+                (
+                    config.dir.join(format!("{}.log", config.output)),
+                    log_string,
+                )
+            }
+            SubCommand::Explore(config) => {
+                let log_string = format!(
+                    r#"tidk version: {}
 Log information for output files: printed to STDOUT
 Date: {}
 `tidk explore` was run with the following parameters:
     Input fasta: {}
-    Explored telomeric repeat units of length: {}
-    Or from length: {}
+    Minimum base quality: {}
+    Explored telomeric repeat units from length: {}
     To length: {}
     Threshold: {}
-    Searching at {}% distance from chromosome end"#,
-                        crate_version!(),
-                        Local::now().format(DATE_FORMAT_STR),
-                        input_fasta.display(),
-                        {
-                            if let Some(l) = length {
-                                l.to_string()
-                            } else {
-                                "None".into()
-                            }
-                        },
-                        {
-                            if let Some(min) = minimum {
-                                min.to_string()
-                            } else {
-                                "None".into()
-                            }
-                        },
-                        {
-                            if let Some(max) = maximum {
-                                max.to_string()
-                            } else {
-                                "None".into()
-                            }
-                        },
-                        // safely unwrap
-                        threshold.unwrap(),
-                        // safely unwrap
-                        *dist_from_chromosome_end.unwrap() * 100.0,
-                    );
-
-                    // create file
-                    let log_file_name = "tidk-explore.log".to_string();
-                    let log_file = std::fs::File::create(&log_file_name)?;
-                    let mut log_file = std::io::LineWriter::new(log_file);
-
-                    writeln!(log_file, "{}", log_string)?;
-
-                    Ok(eprintln!("[+]\tLog file written to: {}", log_file_name))
-                }
-                SubCommand::Search => {
-                    let input_fasta = matches
-                        .get_one::<PathBuf>("fasta")
-                        .expect("errored by clap");
-                    let telomeric_repeat = matches
-                        .get_one::<String>("string")
-                        .expect("errored by clap");
-                    let extension = matches
-                        .get_one::<String>("extension")
-                        .expect("errored by clap");
-
-                    let window_size = matches.get_one::<usize>("window").expect("errored by clap");
-                    let outdir = matches.get_one::<PathBuf>("dir").expect("errored by clap");
-                    let output = matches
-                        .get_one::<PathBuf>("output")
-                        .expect("errored by clap");
-
-                    // create file
-                    let file_name = format!(
-                        "{}/{}{}{}",
-                        outdir.display(),
-                        output.display(),
-                        "_telomeric_repeat_windows.",
-                        extension
-                    );
-
-                    let log_string = format!(
-                        r#"tidk version: {}
+    Searching at {}% distance from chromosome end
+    Exploring reads: {}, with {} bases searched at each read end"#,
+                    crate_version!(),
+                    Local::now().format(DATE_FORMAT_STR),
+                    config.fasta.display(),
+                    log_option(config.min_quality),
+                    config.minimum,
+                    config.maximum,
+                    config.threshold,
+                    config.distance * 100.0,
+                    config.reads,
+                    config.read_end,
+                );
+                (PathBuf::from("tidk-explore.log"), log_string)
+            }
+            SubCommand::Search(config) => {
+                let log_string = format!(
+                    r#"tidk version: {}
 Log information for output file: {}
 Date: {}
 `tidk search` was run with the following parameters:
     Input fasta: {}
+    Minimum base quality: {}
     Telomeric repeat search string: {}
     Window size: {}
-                    "#,
-                        crate_version!(),
-                        file_name,
-                        Local::now().format(DATE_FORMAT_STR),
-                        input_fasta.display(),
-                        telomeric_repeat,
-                        window_size
-                    );
+    Step: {}
+    Output format: {}
+    Soft-masked repeats: {}
+    Densities written: {}
+    Stranded tracks: {}
+    Hits written: {}
+    Hits merged: {}"#,
+                    crate_version!(),
+                    config.windows_file().display(),
+                    Local::now().format(DATE_FORMAT_STR),
+                    config.fasta.display(),
+                    log_option(config.min_quality),
+                    config.telomeric_repeat,
+                    config.window,
+                    log_option(config.step),
+                    config.extension.name(),
+                    config.soft_mask.name(),
+                    config.normalise,
+                    config.stranded,
+                    config.hits.map_or("none".to_string(), |format| config
+                        .hits_file(format)
+                        .display()
+                        .to_string()),
+                    config.merge_hits
+                );
+                (
+                    config.dir.join(format!("{}.log", config.output)),
+                    log_string,
+                )
+            }
+            SubCommand::Reads(config) => {
+                let options = &config.array_options;
+                let log_string = format!(
+                    r#"tidk version: {}
+Log information for output file: {}/{}_telomeric_reads.tsv
+Date: {}
+`tidk reads` was run with the following parameters:
+    Input reads: {}
+    Telomeric repeat: {}
+    Greatest distance from read end: {}
+    Greatest gap between repeats: {}
+    Shortest array: {}
+    Histogram bin width: {}
+    Reads extracted: {}
+    Assembly matched: {}"#,
+                    crate_version!(),
+                    config.dir.display(),
+                    config.output,
+                    Local::now().format(DATE_FORMAT_STR),
+                    config.reads.display(),
+                    config.telomeric_repeat,
+                    options.max_distance,
+                    options.max_gap,
+                    options.min_length,
+                    config.bin_width,
+                    config.extract,
+                    config.assembly.as_ref().map_or("none".to_string(), |path| {
+                        let options = &config.match_options;
+                        format!(
+                            "{} (flank {}, terminal window {}, k {}, at least {} shared k-mers)",
+                            path.display(),
+                            options.flank,
+                            options.window,
+                            options.k,
+                            options.min_shared
+                        )
+                    })
+                );
+                (
+                    config.dir.join(format!("{}.log", config.output)),
+                    log_string,
+                )
+            }
+        };
 
-                    // create file
-                    let log_file_name =
-                        format!("{}/{}{}", outdir.display(), output.display(), ".log");
-                    let log_file = std::fs::File::create(&log_file_name)?;
-                    let mut log_file = std::io::LineWriter::new(log_file);
+        // create file
+        let log_file = std::fs::File::create(&log_file_name)?;
+        let mut log_file = std::io::LineWriter::new(log_file);
 
-                    writeln!(log_file, "{}", log_string)?;
+        writeln!(log_file, "{}", log_string)?;
 
-                    Ok(eprintln!("[+]\tLog file written to: {}", log_file_name))
-                }
-            }
-        } else {
-            Ok(())
-        }
+        eprintln!("[+]\tLog file written to: {}", log_file_name.display());
+        Ok(())
     }
 }
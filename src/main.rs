@@ -1,9 +1,26 @@
-use anyhow::Result;
-use clap::{arg, builder::ArgPredicate, crate_version, value_parser, Arg, Command};
+use clap::{arg, builder::ArgPredicate, crate_version, value_parser, Arg, ArgMatches, Command};
 use std::path::PathBuf;
-use tidk::{build, clades::get_clades, explore, finder, plot, search, SubCommand};
+use tidk::{
+    build,
+    clades::{self, get_clades},
+    ends::MatchOptions,
+    explore::{self, ExploreConfig},
+    finder::{self, FindConfig},
+    plot::{self, Compare, Layout, PlotConfig, PlotFormat, YScale},
+    reads::{self, ArrayOptions, ReadsConfig},
+    search::{self, HitsFormat, SearchConfig},
+    windows::{Extension, SoftMask},
+    Error, Result,
+};
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("[-]\t{}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<()> {
     // command line options
     let matches = Command::new("tidk")
         .version(crate_version!())
@@ -22,15 +39,25 @@ fn main() -> Result<()> {
                     Arg::new("fasta")
                         .value_name("FASTA")
                         .value_parser(value_parser!(PathBuf))
-                        .help("The input fasta file")
+                        .help("The input FASTA or FASTQ file, optionally gzipped")
                         .required_unless_present("print")
                 )
+                .arg(
+                    arg!(--"min-quality" <MIN_QUALITY> "For FASTQ, mask bases below this Phred quality with N before matching")
+                        .value_parser(value_parser!(u8))
+                        .required(false)
+                )
                 .arg(
                     // no longer required.
                     arg!(-w --window [WINDOW] "Window size to calculate telomeric repeat counts in")
                         .value_parser(value_parser!(usize))
                         .default_value("10000")
                 )
+                .arg(
+                    arg!(--step <STEP> "Slide the windows along by this many bases, rather than tiling them [default: the window size]")
+                        .value_parser(value_parser!(usize))
+                        .required(false)
+                )
                 .arg(
                     arg!(-c --clade <CLADE> "The clade of organism to identify telomeres in")
                         .required_unless_present("print")
@@ -41,7 +68,6 @@ fn main() -> Result<()> {
                 )
                 .arg(
                     arg!(-o --output <OUTPUT> "Output filename for the TSVs (without extension)")
-                        .value_parser(value_parser!(PathBuf))
                         .required_unless_present("print")
                 )
                 .arg(
@@ -49,6 +75,25 @@ fn main() -> Result<()> {
                         .required_unless_present("print")
                         .value_parser(value_parser!(PathBuf))
                 )
+                .arg(
+                    arg!(-e --extension [EXTENSION] "The extension, defining the output type of the file")
+                        .default_value("tsv")
+                        .value_parser(["tsv", "bigwig"])
+                )
+                .arg(
+                    arg!(--"soft-masked" [MODE] "Count repeats in soft-masked (lower case) sequence with the rest, skip them, or count them separately in the TSV")
+                        .value_parser(["include", "skip", "separate"])
+                        .default_value("include")
+                )
+                .arg(
+                    arg!(--normalise "In bedGraph and bigWig output, give the proportion of bases which aren't N covered by the repeat, rather than counts")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"bigwig-tracks" [TRACKS] "With bigWig output, a single track of forward + reverse counts, or separate forward, reverse and total tracks")
+                        .value_parser(["total", "stranded"])
+                        .default_value("total")
+                )
                 .arg(
                     arg!(-p --print "Print a table of clades, along with their telomeric sequences")
                         .action(clap::ArgAction::SetTrue)
@@ -66,7 +111,12 @@ fn main() -> Result<()> {
                         .value_name("FASTA")
                         .value_parser(value_parser!(PathBuf))
                         .required(true)
-                        .help("The input fasta file")
+                        .help("The input FASTA or FASTQ file, optionally gzipped")
+                )
+                .arg(
+                    arg!(--"min-quality" <MIN_QUALITY> "For FASTQ, mask bases below this Phred quality with N before matching")
+                        .value_parser(value_parser!(u8))
+                        .required(false)
                 )
                 .arg(
                     arg!(-l --length [LENGTH] "Length of substring")
@@ -88,7 +138,7 @@ fn main() -> Result<()> {
                 )
                 .arg(
                     arg!(-t --threshold [THRESHOLD] "Positions of repeats are only reported if they occur sequentially in a greater number than the threshold")
-                        .value_parser(value_parser!(i32))
+                        .value_parser(value_parser!(usize))
                         .default_value("100")
                 )
                 .arg(
@@ -96,6 +146,15 @@ fn main() -> Result<()> {
                         .value_parser(value_parser!(f64))
                         .default_value("0.01")
                 )
+                .arg(
+                    arg!(--reads "Explore unassembled reads, by the periodic k-mers enriched at their ends, rather than chromosome ends")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"read-end" [READ_END] "With --reads, the bases searched at each end of a read")
+                        .value_parser(value_parser!(usize))
+                        .default_value("2000")
+                )
                 .arg(
                     arg!(-v --verbose "Print verbose output.")
                         .action(clap::ArgAction::SetTrue)
@@ -113,7 +172,12 @@ fn main() -> Result<()> {
                         .value_name("FASTA")
                         .value_parser(value_parser!(PathBuf))
                         .required(true)
-                        .help("The input fasta file")
+                        .help("The input FASTA or FASTQ file, optionally gzipped")
+                )
+                .arg(
+                    arg!(--"min-quality" <MIN_QUALITY> "For FASTQ, mask bases below this Phred quality with N before matching")
+                        .value_parser(value_parser!(u8))
+                        .required(false)
                 )
                 .arg(
                     arg!(-s --string <STRING> "The DNA string to query the genome with")
@@ -124,6 +188,11 @@ fn main() -> Result<()> {
                         .value_parser(value_parser!(usize))
                         .default_value("10000")
                 )
+                .arg(
+                    arg!(--step <STEP> "Slide the windows along by this many bases, rather than tiling them [default: the window size]")
+                        .value_parser(value_parser!(usize))
+                        .required(false)
+                )
                 .arg(
                     arg!(-o --output <OUTPUT> "Output filename for the TSVs (without extension)")
                         .required(true)
@@ -136,7 +205,107 @@ fn main() -> Result<()> {
                 .arg(
                     arg!(-e --extension [EXTENSION] "The extension, defining the output type of the file")
                         .default_value("tsv")
-                        .value_parser(["tsv", "bedgraph"])
+                        .value_parser(["tsv", "bedgraph", "bigwig"])
+                )
+                .arg(
+                    arg!(--"soft-masked" [MODE] "Count repeats in soft-masked (lower case) sequence with the rest, skip them, or count them separately in the TSV")
+                        .value_parser(["include", "skip", "separate"])
+                        .default_value("include")
+                )
+                .arg(
+                    arg!(--normalise "In bedGraph and bigWig output, give the proportion of bases which aren't N covered by the repeat, rather than counts")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"bigwig-tracks" [TRACKS] "With bigWig output, a single track of forward + reverse counts, or separate forward, reverse and total tracks")
+                        .value_parser(["total", "stranded"])
+                        .default_value("total")
+                )
+                .arg(
+                    arg!(--hits <FORMAT> "Also write every non-overlapping repeat occurrence, with its strand")
+                        .value_parser(["bed", "gff3"])
+                        .required(false)
+                )
+                .arg(
+                    arg!(--"merge-hits" "Merge tandem repeat occurrences into runs in the --hits output")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("hits")
+                )
+                .arg(
+                    arg!(--log "Output a log file")
+                        .action(clap::ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("reads")
+                .about("Estimate telomere length from long reads (ONT or HiFi) which start or end in a telomeric array.")
+                .arg(
+                    Arg::new("reads")
+                        .value_name("READS")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(true)
+                        .help("The input reads, as FASTA or FASTQ, optionally gzipped")
+                )
+                .arg(
+                    arg!(-s --string <STRING> "The telomeric repeat to search the reads with")
+                        .required(true)
+                )
+                .arg(
+                    arg!(-o --output <OUTPUT> "Output filename for the TSVs (without extension)")
+                        .required(true)
+                )
+                .arg(
+                    arg!(-d --dir <DIR> "Output directory to write files to")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--"max-distance" [MAX_DISTANCE] "The greatest distance from the read end to the array, to allow for adapters")
+                        .value_parser(value_parser!(usize))
+                        .default_value("100")
+                )
+                .arg(
+                    arg!(--"max-gap" [MAX_GAP] "The greatest gap between repeats in an array, to allow for sequencing errors")
+                        .value_parser(value_parser!(usize))
+                        .default_value("50")
+                )
+                .arg(
+                    arg!(--"min-length" [MIN_LENGTH] "The shortest array reported")
+                        .value_parser(value_parser!(usize))
+                        .default_value("100")
+                )
+                .arg(
+                    arg!(--extract "Also write the reads with a terminal array of at least --min-length to FASTA or FASTQ, as they were read, for targeted assembly")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"bin-width" [BIN_WIDTH] "The bin width of the array length histogram")
+                        .value_parser(value_parser!(usize))
+                        .default_value("1000")
+                )
+                .arg(
+                    arg!(--assembly <ASSEMBLY> "Match the flank of each telomeric read to the contig ends of this assembly (FASTA), and estimate the telomere missing from each end")
+                        .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--flank [FLANK] "With --assembly, the bases next to the array on each read which are matched")
+                        .value_parser(value_parser!(usize))
+                        .default_value("5000")
+                )
+                .arg(
+                    arg!(--"terminal-window" [TERMINAL_WINDOW] "With --assembly, the bases at each contig end which are matched against")
+                        .value_parser(value_parser!(usize))
+                        .default_value("20000")
+                )
+                .arg(
+                    arg!(-k --kmer [KMER] "With --assembly, the k-mer length used to match reads to contig ends (at most 32)")
+                        .value_parser(value_parser!(usize))
+                        .default_value("21")
+                )
+                .arg(
+                    arg!(--"min-shared-kmers" [MIN_SHARED_KMERS] "With --assembly, the fewest k-mers a read must share with a contig end to support it")
+                        .value_parser(value_parser!(usize))
+                        .default_value("20")
                 )
                 .arg(
                     arg!(--log "Output a log file")
@@ -145,13 +314,35 @@ fn main() -> Result<()> {
         )
         .subcommand(
             Command::new("plot")
-                .about("SVG plot of TSV generated from tidk search.")
+                .about("SVG, PNG, PDF or interactive HTML plot of TSV or bedGraph generated from tidk search.")
                 // output file name
                 .arg(
-                    arg!(-t --tsv <TSV> "The input TSV file")
+                    arg!(-t --tsv <TSV> "The input TSV or bedGraph file, detected from its contents. Repeat to compare genomes")
                         .value_parser(value_parser!(PathBuf))
+                        .action(clap::ArgAction::Append)
                         .required(true)
                 )
+                .arg(
+                    arg!(--label <LABEL> "A label for each input file, in the same order. Defaults to the file names")
+                        .action(clap::ArgAction::Append)
+                        .required(false)
+                )
+                .arg(
+                    arg!(--compare [COMPARE] "Overlay the genomes in each subplot, or draw them side by side")
+                        .value_parser(["overlay", "side-by-side"])
+                        .default_value("overlay")
+                )
+                .arg(
+                    arg!(--bed <BED> "A BED file drawn as an annotation track under each chromosome (e.g. gaps, called arrays). Repeat for more tracks")
+                        .value_parser(value_parser!(PathBuf))
+                        .action(clap::ArgAction::Append)
+                        .required(false)
+                )
+                .arg(
+                    arg!(--"id-map" <ID_MAP> "A two column TSV mapping chromosome IDs to shared IDs, to match chromosomes between genomes")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(false)
+                )
                 .arg(
                     arg!(--height [HEIGHT] "The height of subplots (px).")
                         .value_parser(value_parser!(i32))
@@ -163,7 +354,7 @@ fn main() -> Result<()> {
                         .default_value("1000")
                 )
                 .arg(
-                    arg!(-o --output [OUTPUT] "Output filename for the SVG (without extension)")
+                    arg!(-o --output [OUTPUT] "Output filename for the plot (without extension)")
                         .value_parser(value_parser!(PathBuf))
                         .default_value("tidk-plot")
                 )
@@ -177,22 +368,74 @@ fn main() -> Result<()> {
                         .value_parser(value_parser!(i32))
                         .default_value("2")
                 )
+                .arg(
+                    arg!(--"shared-x" "Scale the x axis of every chromosome to the longest chromosome")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"shared-y" "Scale the y axis of every chromosome to the greatest count in the genome")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--density "Plot the repeat density of each window from the TSV, which accounts for N gaps, rather than counts")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"y-scale" [Y_SCALE] "Plot repeat counts on a linear or log10(1 + count) scale")
+                        .value_parser(["linear", "log"])
+                        .default_value("linear")
+                )
+                .arg(
+                    arg!(-f --format [FORMAT] "The output format of the plot. HTML is an interactive version of the stacked layout")
+                        .value_parser(["svg", "png", "pdf", "html"])
+                        .default_value("svg")
+                )
+                .arg(
+                    arg!(--dpi [DPI] "The resolution of PNG output")
+                        .value_parser(value_parser!(f32))
+                        .default_value("96")
+                )
+                .arg(
+                    arg!(--layout [LAYOUT] "Stacked line graphs per chromosome, or a single panel karyotype overview")
+                        .value_parser(["stacked", "karyotype"])
+                        .default_value("stacked")
+                )
             )
         .get_matches();
 
     // feed command line options to each main function
     match matches.subcommand() {
         Some(("find", matches)) => {
-            finder::finder(matches, SubCommand::Find)?;
+            // print table of telomeric sequences
+            if matches.get_flag("print") {
+                clades::print_table()?;
+                std::process::exit(1);
+            }
+            finder::finder(&find_config(matches)?)?;
         }
         Some(("explore", matches)) => {
-            explore::explore(matches, SubCommand::Explore)?;
+            let exploration = explore::explore(&explore_config(matches)?)?;
+            exploration.write_tsv(&mut std::io::stdout())?;
         }
         Some(("search", matches)) => {
-            search::search(matches, SubCommand::Search)?;
+            search::search(&search_config(matches)?)?;
+        }
+        Some(("reads", matches)) => {
+            let summary = reads::reads(&reads_config(matches)?)?;
+            // the summary goes to stdout
+            let lengths = summary.lengths;
+            let stat = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_default();
+            println!("reads\t{}", summary.reads);
+            println!("telomeric_read_ends\t{}", lengths.count);
+            println!("mean\t{}", stat(lengths.mean));
+            println!("median\t{}", stat(lengths.median));
+            println!(
+                "n50\t{}",
+                lengths.n50.map(|n| n.to_string()).unwrap_or_default()
+            );
         }
         Some(("plot", matches)) => {
-            plot::plot(matches)?;
+            plot::plot(&plot_config(matches)?)?;
         }
         Some(("build", _)) => {
             build::fetch_and_save_data()?;
@@ -204,3 +447,148 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// An argument which is required, or has a default, in clap.
+fn value<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, name: &str) -> Result<T> {
+    matches
+        .try_get_one::<T>(name)
+        .ok()
+        .flatten()
+        .cloned()
+        .ok_or_else(|| Error::InvalidOption(format!("The option --{} is missing.", name)))
+}
+
+fn find_config(matches: &ArgMatches) -> Result<FindConfig> {
+    Ok(FindConfig::new(
+        value::<PathBuf>(matches, "fasta")?,
+        &value::<String>(matches, "clade")?,
+        &value::<String>(matches, "output")?,
+        value::<PathBuf>(matches, "dir")?,
+    )
+    .min_quality(matches.get_one::<u8>("min-quality").copied())
+    .window(value(matches, "window")?)
+    .step(matches.get_one::<usize>("step").copied())
+    .extension(Extension::from_name(&value::<String>(
+        matches,
+        "extension",
+    )?)?)
+    .soft_mask(SoftMask::from_name(&value::<String>(
+        matches,
+        "soft-masked",
+    )?)?)
+    .normalise(matches.get_flag("normalise"))
+    .stranded(value::<String>(matches, "bigwig-tracks")? == "stranded")
+    .log(matches.get_flag("log")))
+}
+
+fn explore_config(matches: &ArgMatches) -> Result<ExploreConfig> {
+    // if length is not set, it's zero, and the minimum and maximum are used
+    let length = value::<usize>(matches, "length")?;
+    let config = ExploreConfig::new(value::<PathBuf>(matches, "fasta")?);
+    let config = if length > 0 {
+        config.length(length)
+    } else {
+        config.lengths(value(matches, "minimum")?, value(matches, "maximum")?)
+    };
+    Ok(config
+        .min_quality(matches.get_one::<u8>("min-quality").copied())
+        .threshold(value(matches, "threshold")?)
+        .distance(value(matches, "distance")?)
+        .reads(matches.get_flag("reads"))
+        .read_end(value(matches, "read-end")?)
+        .verbose(matches.get_flag("verbose"))
+        .log(matches.get_flag("log")))
+}
+
+fn search_config(matches: &ArgMatches) -> Result<SearchConfig> {
+    Ok(SearchConfig::new(
+        value::<PathBuf>(matches, "fasta")?,
+        &value::<String>(matches, "string")?,
+        &value::<String>(matches, "output")?,
+        value::<PathBuf>(matches, "dir")?,
+    )
+    .min_quality(matches.get_one::<u8>("min-quality").copied())
+    .window(value(matches, "window")?)
+    .step(matches.get_one::<usize>("step").copied())
+    .extension(Extension::from_name(&value::<String>(
+        matches,
+        "extension",
+    )?)?)
+    .soft_mask(SoftMask::from_name(&value::<String>(
+        matches,
+        "soft-masked",
+    )?)?)
+    .normalise(matches.get_flag("normalise"))
+    .stranded(value::<String>(matches, "bigwig-tracks")? == "stranded")
+    .hits(
+        matches
+            .get_one::<String>("hits")
+            .map(|format| HitsFormat::from_name(format))
+            .transpose()?,
+    )
+    .merge_hits(matches.get_flag("merge-hits"))
+    .log(matches.get_flag("log")))
+}
+
+fn reads_config(matches: &ArgMatches) -> Result<ReadsConfig> {
+    Ok(ReadsConfig::new(
+        value::<PathBuf>(matches, "reads")?,
+        &value::<String>(matches, "string")?,
+        &value::<String>(matches, "output")?,
+        value::<PathBuf>(matches, "dir")?,
+    )
+    .array_options(ArrayOptions {
+        max_distance: value(matches, "max-distance")?,
+        max_gap: value(matches, "max-gap")?,
+        min_length: value(matches, "min-length")?,
+    })
+    .bin_width(value(matches, "bin-width")?)
+    .extract(matches.get_flag("extract"))
+    .assembly(matches.get_one::<PathBuf>("assembly").cloned())
+    .match_options(MatchOptions {
+        flank: value(matches, "flank")?,
+        window: value(matches, "terminal-window")?,
+        k: value(matches, "kmer")?,
+        min_shared: value(matches, "min-shared-kmers")?,
+    })
+    .log(matches.get_flag("log")))
+}
+
+fn plot_config(matches: &ArgMatches) -> Result<PlotConfig> {
+    Ok(PlotConfig::new(
+        matches
+            .get_many::<PathBuf>("tsv")
+            .ok_or_else(|| {
+                Error::InvalidOption("No TSV or bedGraph was given to plot.".to_string())
+            })?
+            .cloned()
+            .collect(),
+    )
+    .labels(
+        matches
+            .get_many::<String>("label")
+            .map(|labels| labels.cloned().collect()),
+    )
+    .compare(Compare::from_name(&value::<String>(matches, "compare")?)?)
+    .beds(
+        matches
+            .get_many::<PathBuf>("bed")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+    )
+    .id_map(matches.get_one::<PathBuf>("id-map").cloned())
+    .height(value(matches, "height")?)
+    .width(value(matches, "width")?)
+    .output(value::<PathBuf>(matches, "output")?)
+    .font_size(value(matches, "fontsize")?)
+    .stroke_width(value(matches, "strokewidth")?)
+    .shared_x(matches.get_flag("shared-x"))
+    .shared_y(matches.get_flag("shared-y"))
+    .density(matches.get_flag("density"))
+    .y_scale(YScale::from_name(&value::<String>(matches, "y-scale")?)?)
+    .format(PlotFormat::from_name(&value::<String>(matches, "format")?)?)
+    .dpi(value(matches, "dpi")?)
+    .layout(Layout::from_name(&value::<String>(matches, "layout")?)?))
+}
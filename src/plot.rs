@@ -1,90 +1,745 @@
-use anyhow::Result;
+use crate::{utils, Error, Result};
 use csv::ReaderBuilder;
+use resvg::{tiny_skia, usvg};
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 
 /// The plot margins
 const MARGIN: i32 = 40;
 
-/// The entry point for `tidk plot`.
-pub fn plot(matches: &clap::ArgMatches) -> Result<()> {
-    // parse the command line options
-    let tsv = matches.get_one::<PathBuf>("tsv").expect("errored by clap");
-    // a bug here for manual input of chromosome cut-off which I can't figure out right now.
-    let chromosome_cutoff = 0;
-    let height_subplot = matches.get_one::<i32>("height").expect("defualted by clap");
-    let width = matches.get_one::<i32>("width").expect("defaulted by clap");
-    let output = matches
-        .get_one::<PathBuf>("output")
-        .expect("defaulted by clap");
-    let font_size = matches
-        .get_one::<i32>("fontsize")
-        .expect("defaulted by clap");
-    let stroke_width = matches
-        .get_one::<i32>("strokewidth")
-        .expect("defaulted by clap");
-
-    // parse the tsv
-    let parsed_tsv = parse_tsv(tsv.to_path_buf())?;
-
-    // calculate the number of chromosomes to plot with the length cutoff
-    let chromosome_number = chromosome_number(&parsed_tsv, chromosome_cutoff);
-
-    // height of plot
-    let height: i32 = height_subplot * chromosome_number as i32 + (2 * MARGIN);
-
-    // generate the plot data (see struct PlotData)
-    let plot_data = generate_plot_data(parsed_tsv, height, *width, *height_subplot);
-
-    // filter the data based on the cutoff
-    let plot_data_filtered: Vec<PlotData> = plot_data
-        .into_iter()
-        .filter(|x| x.max > chromosome_cutoff as usize)
-        .collect();
+/// The options of `tidk plot`. Build with [`PlotConfig::new()`], then
+/// set any options which aren't the defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotConfig {
+    /// The TSV or bedGraph of each genome.
+    pub tsvs: Vec<PathBuf>,
+    /// A label for each genome, defaulting to the file names.
+    pub labels: Option<Vec<String>>,
+    pub compare: Compare,
+    /// BED files drawn as annotation tracks.
+    pub beds: Vec<PathBuf>,
+    /// A two column TSV renaming chromosome IDs.
+    pub id_map: Option<PathBuf>,
+    /// The height of each subplot (px).
+    pub height: i32,
+    /// The width of the plot (px).
+    pub width: i32,
+    /// The output file name, without extension.
+    pub output: PathBuf,
+    pub font_size: i32,
+    pub stroke_width: i32,
+    pub shared_x: bool,
+    pub shared_y: bool,
+    /// Plot repeat densities, rather than counts.
+    pub density: bool,
+    pub y_scale: YScale,
+    pub format: PlotFormat,
+    /// The resolution of PNG output.
+    pub dpi: f32,
+    pub layout: Layout,
+}
+
+impl PlotConfig {
+    /// The default options, plotting `tsvs`.
+    pub fn new(tsvs: Vec<PathBuf>) -> Self {
+        Self {
+            tsvs,
+            labels: None,
+            compare: Compare::Overlay,
+            beds: Vec::new(),
+            id_map: None,
+            height: 200,
+            width: 1000,
+            output: PathBuf::from("tidk-plot"),
+            font_size: 12,
+            stroke_width: 2,
+            shared_x: false,
+            shared_y: false,
+            density: false,
+            y_scale: YScale::Linear,
+            format: PlotFormat::Svg,
+            dpi: 96.0,
+            layout: Layout::Stacked,
+        }
+    }
+
+    pub fn labels(mut self, labels: Option<Vec<String>>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn compare(mut self, compare: Compare) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    pub fn beds(mut self, beds: Vec<PathBuf>) -> Self {
+        self.beds = beds;
+        self
+    }
+
+    pub fn id_map(mut self, id_map: Option<PathBuf>) -> Self {
+        self.id_map = id_map;
+        self
+    }
+
+    pub fn height(mut self, height: i32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn width(mut self, width: i32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = output.into();
+        self
+    }
+
+    pub fn font_size(mut self, font_size: i32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn stroke_width(mut self, stroke_width: i32) -> Self {
+        self.stroke_width = stroke_width;
+        self
+    }
+
+    pub fn shared_x(mut self, shared_x: bool) -> Self {
+        self.shared_x = shared_x;
+        self
+    }
+
+    pub fn shared_y(mut self, shared_y: bool) -> Self {
+        self.shared_y = shared_y;
+        self
+    }
+
+    pub fn density(mut self, density: bool) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn y_scale(mut self, y_scale: YScale) -> Self {
+        self.y_scale = y_scale;
+        self
+    }
+
+    pub fn format(mut self, format: PlotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn dpi(mut self, dpi: f32) -> Self {
+        self.dpi = dpi;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+/// How several genomes are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    /// In the same subplot.
+    Overlay,
+    /// In columns, sharing the axes of each chromosome.
+    SideBySide,
+}
+
+impl Compare {
+    /// From the value of `--compare`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "overlay" => Ok(Compare::Overlay),
+            "side-by-side" => Ok(Compare::SideBySide),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown comparison {}, expected overlay or side-by-side.",
+                name
+            ))),
+        }
+    }
+}
+
+/// The layout of the plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// A line graph per chromosome.
+    Stacked,
+    /// A single panel overview of the genome.
+    Karyotype,
+}
+
+impl Layout {
+    /// From the value of `--layout`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "stacked" => Ok(Layout::Stacked),
+            "karyotype" => Ok(Layout::Karyotype),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown layout {}, expected stacked or karyotype.",
+                name
+            ))),
+        }
+    }
+}
+
+/// The file format of the plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlotFormat {
+    Svg,
+    Png,
+    Pdf,
+    /// An interactive version of the stacked layout.
+    Html,
+}
+
+impl PlotFormat {
+    /// From the value of `--format`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "svg" => Ok(PlotFormat::Svg),
+            "png" => Ok(PlotFormat::Png),
+            "pdf" => Ok(PlotFormat::Pdf),
+            "html" => Ok(PlotFormat::Html),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown plot format {}, expected svg, png, pdf or html.",
+                name
+            ))),
+        }
+    }
+
+    /// The file extension.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlotFormat::Svg => "svg",
+            PlotFormat::Png => "png",
+            PlotFormat::Pdf => "pdf",
+            PlotFormat::Html => "html",
+        }
+    }
+}
 
-    // make the writable svg file
-    let out_filename = format!("{}.svg", output.display());
-    let mut svg_file = File::create(out_filename)?;
+/// The entry point for `tidk plot`. Returns the file written.
+pub fn plot(config: &PlotConfig) -> Result<PathBuf> {
+    let tsvs = &config.tsvs;
+    let labels: Vec<String> = match &config.labels {
+        Some(labels) => labels.clone(),
+        // default to the file names
+        None => tsvs
+            .iter()
+            .map(|tsv| {
+                tsv.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| tsv.display().to_string())
+            })
+            .collect(),
+    };
+    if labels.len() != tsvs.len() {
+        return Err(Error::InvalidOption(format!(
+            "{} labels were given for {} input files.",
+            labels.len(),
+            tsvs.len()
+        )));
+    }
+    let id_map = match &config.id_map {
+        Some(path) => parse_id_map(path)?,
+        None => HashMap::new(),
+    };
+    let height_subplot = config.height;
+    let width = config.width;
+    let font_size = config.font_size;
+    let stroke_width = config.stroke_width;
+    let (layout, format) = (config.layout, config.format);
+    let (shared_x, shared_y) = (config.shared_x, config.shared_y);
+    let y_scale = config.y_scale;
+
+    if format == PlotFormat::Html && layout == Layout::Karyotype {
+        return Err(Error::InvalidOption(
+            "The interactive HTML plot only has the stacked layout.".to_string(),
+        ));
+    }
+
+    // annotation tracks drawn under each chromosome
+    if !config.beds.is_empty() && (format == PlotFormat::Html || layout == Layout::Karyotype) {
+        return Err(Error::InvalidOption(
+            "BED annotation tracks are only drawn on the stacked layout, as SVG, PNG or PDF."
+                .to_string(),
+        ));
+    }
+    let mut annotations = Vec::new();
+    for bed in &config.beds {
+        let mut annotation = parse_bed(bed)?;
+        for feature in &mut annotation.features {
+            if let Some(id) = id_map.get(&feature.id) {
+                feature.id = id.clone();
+            }
+        }
+        annotations.push(annotation);
+    }
+
+    // parse the tsv or bedgraph of each genome. Telomeric arrays can
+    // only be called from repeat counts, where the length of each
+    // repeat is known.
+    let mut genomes = Vec::new();
+    let mut repeat_counts = !config.density;
+    for tsv in tsvs {
+        repeat_counts &= detect_format(tsv)? == InputFormat::Tsv;
+        let mut rows = parse_input(tsv, config.density)?;
+        rename_ids(&mut rows, &id_map);
+        genomes.push(rows);
+    }
+
+    // group the windows by chromosome, then by telomeric repeat. With more
+    // than one genome, each subplot holds the matching chromosome of every
+    // genome. The karyotype can't overlay genomes, so always compares them
+    // side by side.
+    let side_by_side =
+        genomes.len() > 1 && (config.compare == Compare::SideBySide || layout == Layout::Karyotype);
+    let grid = if side_by_side {
+        comparison_grid(genomes.into_iter().map(group_by_chromosome).collect())
+    } else {
+        let rows = if genomes.len() == 1 {
+            genomes.remove(0)
+        } else {
+            overlay(genomes, &labels)
+        };
+        group_by_chromosome(rows)
+            .into_iter()
+            .map(|x| vec![Some(x)])
+            .collect()
+    };
+    let repeats = distinct_repeats(grid.iter().flatten().flatten());
+
+    // the karyotype and the interactive plot have a single column, so
+    // the genomes being compared are interleaved
+    let interleave = |grid: Vec<Vec<Option<ChromosomeWindows>>>| -> Vec<ChromosomeWindows> {
+        if side_by_side {
+            interleave_genomes(grid, &labels)
+        } else {
+            grid.into_iter().flatten().flatten().collect()
+        }
+    };
+
+    // the interactive plot is drawn in the browser, not from the svg
+    let out_filename = PathBuf::from(format!("{}.{}", config.output.display(), format.name()));
+    if format == PlotFormat::Html {
+        let html = html_document(
+            &interleave(grid),
+            &repeats,
+            &HtmlOptions {
+                width,
+                height_subplot,
+                stroke_width,
+                shared_x,
+                shared_y,
+                y_scale,
+            },
+        )?;
+        File::create(&out_filename)?.write_all(html.as_bytes())?;
+        eprintln!("[+]\tPlot written to: {}", out_filename.display());
+        return Ok(out_filename);
+    }
+
+    let (height, body) = match layout {
+        Layout::Karyotype => {
+            if !repeat_counts {
+                eprintln!(
+                    "[+]\tTelomeric arrays are only called from repeat counts, so are not marked"
+                );
+            }
+            karyotype(&interleave(grid), width, font_size, y_scale, repeat_counts)
+        }
+        Layout::Stacked => {
+            let stacked = Stacked {
+                height_subplot,
+                width,
+                font_size,
+                stroke_width,
+                shared_x,
+                shared_y,
+                y_scale,
+                columns: grid.first().map(|row| row.len()).unwrap_or(1),
+                tracks: annotations.len(),
+                legend_rows: legend_layout(&repeats, width, font_size)
+                    .last()
+                    .map_or(1, |(_, row)| row + 1),
+            };
+
+            // height of plot
+            let height = stacked.height(grid.len());
+
+            // generate the plot data (see struct PlotData)
+            let plot_data = generate_comparison_plot_data(grid, &labels, &annotations, &stacked);
+
+            (height, add_all_path_elements(plot_data, &repeats, &stacked))
+        }
+    };
 
     // construct the svg
-    let svg = format!(
-            "<?xml version='1.0' encoding='UTF-8'  standalone='no' ?> <!DOCTYPE svg \
-                 PUBLIC '-//W3C//DTD SVG 1.0//EN' \
-                 'http://www.w3.org/TR/2001/REC-SVG-20010904/DTD/svg10.dtd'> <svg version='1.0' \
-                 width='{}' height='{}' xmlns='http://www.w3.org/2000/svg' \
-                 xmlns:xlink='http://www.w3.org/1999/xlink'> \
-                \
-                 <style type='text/css'> \
-                 .chromosome_line:hover {{ stroke-opacity: 1.0; stroke: crimson; stroke-width: 2; }} \
-                 </style> \
-                \
-                 {} \
-                 </svg>",
-            width,
-            height,
-            add_all_path_elements(plot_data_filtered, *height_subplot as isize, *width, *font_size, *stroke_width)
-        );
+    let svg = svg_document(width, height, &body);
 
-    svg_file.write_all(svg.as_bytes())?;
+    // and write it out, in the chosen format
+    let mut plot_file = File::create(&out_filename)?;
+    match format {
+        PlotFormat::Png => plot_file.write_all(&render_png(&svg, config.dpi)?)?,
+        PlotFormat::Pdf => plot_file.write_all(&render_pdf(&svg)?)?,
+        _ => plot_file.write_all(svg.as_bytes())?,
+    }
+    eprintln!("[+]\tPlot written to: {}", out_filename.display());
+
+    Ok(out_filename)
+}
+
+/// Parse our SVG into a tree which can be rendered, with
+/// the system fonts loaded so the labels are drawn.
+fn parse_svg(svg: &str) -> Result<usvg::Tree> {
+    let mut options = usvg::Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+    // the generic monospace family defaults to Courier New, which
+    // is often missing on Linux, so use any monospaced font we have.
+    let monospace = fontdb
+        .faces()
+        .find(|face| face.monospaced)
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if let Some(family) = monospace {
+        fontdb.set_monospace_family(family);
+    }
+    let tree = usvg::Tree::from_str(svg, &options)
+        .map_err(|e| Error::Plot(format!("Could not parse the plot: {}", e)))?;
+    Ok(tree)
+}
+
+/// Rasterise the SVG to PNG bytes. The SVG is sized in px at
+/// 96 DPI, so this is scaled up to the requested DPI.
+fn render_png(svg: &str, dpi: f32) -> Result<Vec<u8>> {
+    let tree = parse_svg(svg)?;
+    let zoom = dpi / 96.0;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(zoom)
+        .ok_or_else(|| Error::Plot(format!("Could not scale the plot to {} DPI.", dpi)))?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        Error::Plot("Could not allocate an image of the size of the plot.".to_string())
+    })?;
+    // PNGs are otherwise transparent
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(zoom, zoom),
+        &mut pixmap.as_mut(),
+    );
+
+    let png = pixmap
+        .encode_png()
+        .map_err(|e| Error::Plot(format!("Could not encode the plot as PNG: {}", e)))?;
+    Ok(png)
+}
+
+/// Convert the SVG to a single page PDF, keeping the text and
+/// lines as vectors.
+fn render_pdf(svg: &str) -> Result<Vec<u8>> {
+    let tree = parse_svg(svg)?;
+    svg2pdf::to_pdf(
+        &tree,
+        svg2pdf::ConversionOptions::default(),
+        svg2pdf::PageOptions::default(),
+    )
+    .map_err(|e| Error::Plot(format!("Could not convert the plot to PDF: {}", e)))
+}
+
+/// The template for the interactive HTML plot. The data is
+/// substituted for `/*TIDK_DATA*/`.
+const HTML_TEMPLATE: &str = include_str!("plot.html");
+
+/// The options of the stacked layout which carry over to the
+/// interactive HTML plot.
+struct HtmlOptions {
+    width: i32,
+    height_subplot: i32,
+    stroke_width: i32,
+    shared_x: bool,
+    shared_y: bool,
+    y_scale: YScale,
+}
+
+/// A self-contained HTML page with an interactive version of the
+/// stacked layout. The windows are embedded as JSON, with each
+/// window as `[start, end, forward, reverse]`.
+fn html_document(
+    chromosomes: &[ChromosomeWindows],
+    repeats: &[String],
+    options: &HtmlOptions,
+) -> Result<String> {
+    let styles: Vec<serde_json::Value> = repeats
+        .iter()
+        .enumerate()
+        .map(|(n, sequence)| {
+            let (colour, dash) = series_style(n);
+            json!({ "sequence": sequence, "colour": colour, "dash": dash })
+        })
+        .collect();
+
+    let chromosomes: Vec<serde_json::Value> = chromosomes
+        .iter()
+        .map(|chromosome| {
+            let series: Vec<serde_json::Value> = chromosome
+                .repeats
+                .iter()
+                .map(|(sequence, windows)| {
+                    let windows: Vec<(i64, i64, f64, f64)> = windows
+                        .iter()
+                        .map(|w| (w.start, w.end, w.forward, w.reverse))
+                        .collect();
+                    json!({
+                        "sequence": sequence,
+                        "style": repeats.iter().position(|r| r == sequence).unwrap_or(0),
+                        "windows": windows,
+                    })
+                })
+                .collect();
+            json!({
+                "id": chromosome.id,
+                "length": chromosome.max,
+                "count_max": chromosome.count_max(),
+                "repeats": series,
+            })
+        })
+        .collect();
+
+    let data = json!({
+        "width": options.width,
+        "height": options.height_subplot,
+        "stroke_width": options.stroke_width,
+        "shared_x": options.shared_x,
+        "shared_y": options.shared_y,
+        "log": options.y_scale == YScale::Log,
+        "repeats": styles,
+        "chromosomes": chromosomes,
+    });
 
-    Ok(())
+    // chromosome IDs must not be able to close the script tag
+    let data = serde_json::to_string(&data)
+        .map_err(|e| Error::Plot(format!("Could not write the plot data: {}", e)))?
+        .replace("</", "<\\/");
+    Ok(HTML_TEMPLATE.replace("/*TIDK_DATA*/", &data))
 }
 
-/// Deserialise the TSV records into a struct.
+/// Wrap the plot elements in an SVG document.
+fn svg_document(width: i32, height: i32, body: &str) -> String {
+    format!(
+        "<?xml version='1.0' encoding='UTF-8'  standalone='no' ?> <!DOCTYPE svg \
+             PUBLIC '-//W3C//DTD SVG 1.0//EN' \
+             'http://www.w3.org/TR/2001/REC-SVG-20010904/DTD/svg10.dtd'> <svg version='1.0' \
+             width='{}' height='{}' xmlns='http://www.w3.org/2000/svg' \
+             xmlns:xlink='http://www.w3.org/1999/xlink'> \
+            \
+             <style type='text/css'> \
+             .chromosome_line:hover {{ stroke-opacity: 1.0; stroke-width: 4; }} \
+             .karyotype_window:hover {{ stroke: black; stroke-width: 1; }} \
+             </style> \
+            \
+             {} \
+             </svg>",
+        width, height, body
+    )
+}
+
+/// Escape text for an SVG element or attribute value, as sequence ids,
+/// track names and features come straight from the input files.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Deserialise the TSV records into a struct. Reads both the current
+/// TSV, with the `start` and `end` of each window, and the first version,
+/// with only the end of each window in `window`.
 #[derive(Debug, Deserialize)]
 pub struct TelomericRepeatRecord {
     pub id: String,
-    pub window: i64,
+    /// The end of the window, in the first version of the TSV.
+    #[serde(default)]
+    pub window: Option<i64>,
+    #[serde(default)]
+    pub start: Option<i64>,
+    #[serde(default)]
+    pub end: Option<i64>,
     pub forward_repeat_number: i32,
     pub reverse_repeat_number: i32,
     pub telomeric_repeat: String,
+    #[serde(default)]
+    pub n_count: Option<i64>,
+    #[serde(default)]
+    pub masked_count: Option<i64>,
+    #[serde(default)]
+    pub gc_content: Option<f64>,
+    #[serde(default)]
+    pub repeat_density: Option<f64>,
+}
+
+/// The formats `tidk plot` can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    /// The TSV from `tidk search` or `tidk find`.
+    Tsv,
+    /// A four column bedGraph, e.g. from `tidk search -e bedgraph`.
+    BedGraph,
+}
+
+/// Is this a bedGraph line which carries no data?
+fn is_bedgraph_header(line: &str) -> bool {
+    line.trim().is_empty()
+        || line.starts_with('#')
+        || line.starts_with("track")
+        || line.starts_with("browser")
+}
+
+/// The `name` of a track line, e.g. `track type=bedGraph name="my track"`.
+/// Values may be quoted, in which case they can hold spaces.
+fn track_name(line: &str) -> Option<String> {
+    let mut rest = line.strip_prefix("track")?;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+        let Some(value) = rest.strip_prefix('=') else {
+            // a bare word, not a key=value pair
+            continue;
+        };
+        let (value, remainder) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        if key == "name" {
+            return Some(value.to_string());
+        }
+        rest = remainder;
+    }
+}
+
+/// Detect the input format from the first line with data. The TSV
+/// always has a header starting with `id`, whereas a bedGraph has
+/// numeric start and end columns.
+fn detect_format(path: &Path) -> Result<InputFormat> {
+    let reader = BufReader::new(utils::open_file(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if is_bedgraph_header(&line) {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.first() == Some(&"id") {
+            return Ok(InputFormat::Tsv);
+        }
+        if fields.len() == 4 && fields[1].parse::<i64>().is_ok() && fields[2].parse::<i64>().is_ok()
+        {
+            return Ok(InputFormat::BedGraph);
+        }
+        return Err(Error::Parse(format!(
+            "Could not recognise {} as a tidk TSV or a bedGraph.",
+            path.display()
+        )));
+    }
+    Err(Error::EmptyInput(path.to_path_buf()))
+}
+
+/// Parse either input format into rows.
+fn parse_input(path: &Path, density: bool) -> Result<Vec<Row>> {
+    match detect_format(path)? {
+        InputFormat::Tsv if density => density_rows(parse_tsv(path.to_path_buf())?),
+        InputFormat::Tsv => Ok(tsv_rows(parse_tsv(path.to_path_buf())?)),
+        InputFormat::BedGraph => {
+            eprintln!("[+]\tReading {} as a bedGraph", path.display());
+            parse_bedgraph(BufReader::new(utils::open_file(path)?))
+        }
+    }
+}
+
+/// Parse a bedGraph into rows. The value is taken as the forward
+/// count, and the track name (if any) in place of the telomeric repeat.
+fn parse_bedgraph<R: BufRead>(reader: R) -> Result<Vec<Row>> {
+    let mut name = "bedGraph".to_string();
+    let mut rows = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let Some(n) = track_name(&line) {
+            name = n;
+        }
+        if is_bedgraph_header(&line) {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(Error::Parse(format!(
+                "Line {} of the bedGraph has {} columns, expected 4.",
+                i + 1,
+                fields.len()
+            )));
+        }
+        let parse_error =
+            || Error::Parse(format!("Could not parse line {} of the bedGraph.", i + 1));
+        rows.push(Row {
+            id: fields[0].to_string(),
+            repeat: name.clone(),
+            window: Window {
+                start: fields[1].parse().map_err(|_| parse_error())?,
+                end: fields[2].parse().map_err(|_| parse_error())?,
+                forward: fields[3].parse().map_err(|_| parse_error())?,
+                reverse: 0.0,
+            },
+        });
+    }
+    Ok(rows)
 }
 
 /// This deserializes a TSV to a [`Vec<TelomericRepeatRecord>`].
 fn parse_tsv(path: PathBuf) -> Result<Vec<TelomericRepeatRecord>> {
-    let mut tsv_reader = ReaderBuilder::new().delimiter(b'\t').from_path(path)?;
+    // skipping the version line
+    let mut tsv_reader = ReaderBuilder::new()
+        .delimiter(b'\t')
+        .comment(Some(b'#'))
+        .from_path(path)?;
     let mut plot_coords_vec = Vec::new();
 
     for result in tsv_reader.deserialize() {
@@ -95,150 +750,553 @@ fn parse_tsv(path: PathBuf) -> Result<Vec<TelomericRepeatRecord>> {
     Ok(plot_coords_vec)
 }
 
-/// Takes the parsed TSV and the chromosome cutoff,
-/// loops through file to find the lengths of all the
-/// chromosomes (to the nearest window) and reports the
-/// number of elements.
-fn chromosome_number(parsed_tsv: &Vec<TelomericRepeatRecord>, chromosome_cutoff: i32) -> usize {
-    // so we can break the loop
-    let file_length = parsed_tsv.len();
-    // the iteration of the loop
-    let mut it = 0usize;
-    // a mutable vector to calculate svg path attribute
-    let mut max_sizes = Vec::new();
+/// Scale a range [min, max] to custom range [a, b].
+/// Our range will be [0, height of subplots].
+fn scale_y(y: f64, a: f64, b: f64, min: f64, max: f64) -> f64 {
+    (((b - a) * (y - min)) / (max - min)) + a
+}
 
-    loop {
-        if it == file_length - 1 {
-            break;
+/// The dimensions and options of the stacked layout, where each
+/// chromosome has its own line graph.
+struct Stacked {
+    /// The height of each subplot (px).
+    height_subplot: i32,
+    /// The width of the plot (px).
+    width: i32,
+    font_size: i32,
+    stroke_width: i32,
+    /// Scale the x axis of every subplot to the longest chromosome,
+    /// rather than to its own chromosome.
+    shared_x: bool,
+    /// Scale the y axis of every subplot to the greatest count in
+    /// the genome, rather than on its own chromosome.
+    shared_y: bool,
+    y_scale: YScale,
+    /// The number of genomes compared side by side.
+    columns: usize,
+    /// The number of annotation tracks under each subplot.
+    tracks: usize,
+    /// The number of rows of the legend, see [`legend_layout()`].
+    legend_rows: usize,
+}
+
+/// How repeat counts are mapped onto the y axis (or onto the
+/// colour scale in the karyotype layout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YScale {
+    Linear,
+    /// log10(1 + count), so windows without repeats stay at zero.
+    Log,
+}
+
+impl YScale {
+    /// From the value of `--y-scale`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "linear" => Ok(YScale::Linear),
+            "log" => Ok(YScale::Log),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown y scale {}, expected linear or log.",
+                name
+            ))),
         }
+    }
 
-        if parsed_tsv[it].id == parsed_tsv[it + 1].id {
-            it += 1;
-            continue;
-        } else {
-            if parsed_tsv[it].window > chromosome_cutoff.into() {
-                max_sizes.push(parsed_tsv[it].window);
+    /// Transform a count onto the scale.
+    fn transform(&self, count: f64) -> f64 {
+        match self {
+            YScale::Linear => count,
+            YScale::Log => (1.0 + count).log10(),
+        }
+    }
+
+    /// The ticks for an axis from zero to `max`, as untransformed counts.
+    fn ticks(&self, max: f64) -> Vec<f64> {
+        match self {
+            // whole counts, unless plotting densities of at most one
+            YScale::Linear => nice_ticks(max, 4, if max > 1.0 { 1.0 } else { 0.0 }),
+            YScale::Log => {
+                let mut ticks = vec![0.0];
+                let mut tick = 1.0;
+                while tick <= max {
+                    ticks.push(tick);
+                    tick *= 10.0;
+                }
+                ticks
             }
-            it += 1;
         }
     }
-    // add 1 as we can never compare the last entry.
-    max_sizes.len() + 1
 }
 
-/// Scale a range [min, max] to custom range [a, b].
-/// Our range will be [0, height of subplots].
-fn scale_y(y: f64, a: f64, b: f64, min: f64, max: f64) -> f64 {
-    (((b - a) * (y - min)) / (max - min)) + a
+/// The plotting area of a subplot in the SVG (px), excluding
+/// its labels and ticks.
+#[derive(Debug, Clone, Copy)]
+struct Subplot {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Subplot {
+    fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+}
+
+impl Stacked {
+    /// The height of the whole plot, given the number of chromosomes.
+    fn height(&self, chromosome_number: usize) -> i32 {
+        self.row_height() * chromosome_number as i32 + self.top() + MARGIN
+    }
+
+    /// The top of the first subplot, below the legend (px).
+    fn top(&self) -> i32 {
+        MARGIN + self.legend_rows.saturating_sub(1) as i32 * legend_row_height(self.font_size)
+    }
+
+    /// The height of an annotation track (px).
+    fn track_height(&self) -> i32 {
+        self.font_size + 4
+    }
+
+    /// The height of a subplot and its annotation tracks (px).
+    fn row_height(&self) -> i32 {
+        self.height_subplot + self.tracks as i32 * self.track_height()
+    }
+
+    /// The plotting area of the subplot in the ith row, counted from
+    /// the top, and in the given column, counted from the left.
+    fn subplot(&self, i: usize, column: usize) -> Subplot {
+        // room for the chromosome label above and the x tick labels below
+        let label_height = (self.font_size + 8) as f64;
+        let tick_height = (self.font_size + 8) as f64;
+        let column_width = (self.width - MARGIN) as f64 / self.columns.max(1) as f64;
+        // room for the y tick labels on the left
+        let gutter = (MARGIN + self.font_size * 4) as f64;
+        Subplot {
+            left: column as f64 * column_width + gutter,
+            top: (self.top() + i as i32 * self.row_height()) as f64 + label_height,
+            width: (column_width - gutter).max(1.0),
+            height: (self.height_subplot as f64 - label_height - tick_height).max(1.0),
+        }
+    }
 }
 
 /// Make the SVG path elements:
 /// `Mx,yLx1,y1Lx2,y2`...
 ///
-/// This is also where the paths are scaled to
-/// the plot width and subplot height.
+/// This is also where the paths are scaled to the subplot. Each
+/// count is placed at the midpoint of its window, in genomic
+/// coordinates from zero to `x_max`.
 fn make_path_element(
-    path_vec: Vec<(i32, i32)>,
-    x_max: usize,
-    y_max: usize,
-    height: i32,
-    width: i32,
-    height_per_plot: i32,
+    windows: &[Window],
+    x_max: f64,
+    y_max: f64,
+    y_scale: YScale,
+    subplot: &Subplot,
 ) -> Option<String> {
-    let subplot_gap = 25.0;
     // need this here...
-    if path_vec.is_empty() {
+    if windows.is_empty() {
         return None;
     }
 
-    // somehow going to have to scale the lines to fit on all graphs
     let mut path = String::new();
-    // margin at either side of the plot
-    let width_incl_margins = width - (MARGIN * 2);
-
-    let x_bin: f64 = width_incl_margins as f64 / x_max as f64;
-
-    // add the first move to point
-    path += &format!(
-        "M{},{}",
-        MARGIN as f64 / 2f64, // + x_bin, don't think I need x_bin here?
-        // y is height - repeat number, a = 0, b = height_per_plot
-        // min is again zero (no negative repeats), max is greatest repeats per chromosome
-        height as f64
-            - MARGIN as f64
-            - scale_y(
-                path_vec[0].1 as f64,
-                0.0,
-                height_per_plot as f64,
-                0.0,
-                y_max as f64 + subplot_gap
-            )
-    );
-
-    let mut bin: f64 = x_bin;
-    for element in path_vec.iter().skip(1) {
+    for (i, window) in windows.iter().enumerate() {
+        let midpoint = (window.start + window.end) as f64 / 2.0;
         path += &format!(
-            "L{},{}",
-            bin + (MARGIN as f64 / 2.0),
-            //
-            height as f64
-                - MARGIN as f64
+            "{}{},{}",
+            if i == 0 { "M" } else { "L" },
+            subplot.left + scale_y(midpoint, 0.0, subplot.width, 0.0, x_max),
+            // min is zero (no negative repeats), max is greatest repeats per chromosome
+            subplot.bottom()
                 - scale_y(
-                    element.1 as f64,
+                    y_scale.transform(window.count()),
                     0.0,
-                    height_per_plot as f64,
+                    subplot.height,
                     0.0,
-                    y_max as f64 + subplot_gap
+                    y_scale.transform(y_max)
                 )
         );
-        bin += x_bin;
     }
     Some(path)
 }
 
-/// Add the path elements from Vec<PlotData.path> to their SVG tags.
+/// Roughly `n` evenly spaced ticks from zero up to `max`, in steps
+/// of 1, 2 or 5 x 10^k, and no smaller than `min_step`.
+fn nice_ticks(max: f64, n: usize, min_step: f64) -> Vec<f64> {
+    if max <= 0.0 {
+        return vec![0.0];
+    }
+    let raw_step = max / n as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10.0 * magnitude)
+        .max(min_step);
+
+    (0..)
+        .map(|i| i as f64 * step)
+        .take_while(|tick| *tick <= max * (1.0 + 1e-9))
+        .collect()
+}
+
+/// Format a genomic position as bp, kb or Mb. The unit depends on the
+/// length of the axis, and the number of decimals on the tick spacing.
+fn format_position(position: f64, step: f64, axis_max: f64) -> String {
+    let (unit, divisor) = if axis_max >= 1_000_000.0 {
+        ("Mb", 1_000_000.0)
+    } else if axis_max >= 1_000.0 {
+        ("kb", 1_000.0)
+    } else {
+        ("bp", 1.0)
+    };
+    let scaled_step = step / divisor;
+    let decimals = if scaled_step >= 1.0 || scaled_step <= 0.0 {
+        0
+    } else {
+        (-scaled_step.log10()).ceil() as usize
+    };
+    format!("{:.*}{}", decimals, position / divisor, unit)
+}
+
+/// Colours given to each telomeric repeat in turn. When there are more
+/// repeats than colours, the palette is reused with a dashed stroke.
+const PALETTE: [&str; 8] = [
+    "black",
+    "crimson",
+    "steelblue",
+    "darkorange",
+    "seagreen",
+    "purple",
+    "goldenrod",
+    "teal",
+];
+
+/// Return the colour and the dash pattern of the nth telomeric repeat.
+fn series_style(n: usize) -> (&'static str, &'static str) {
+    let colour = PALETTE[n % PALETTE.len()];
+    let dash = match n / PALETTE.len() {
+        0 => "none",
+        1 => "6,3",
+        2 => "2,2",
+        _ => "6,2,2,2",
+    };
+    (colour, dash)
+}
+
+/// The height of a row of the legend (px).
+fn legend_row_height(font_size: i32) -> i32 {
+    font_size + 6
+}
+
+/// The width of a legend entry (px): its line, then its label in a
+/// monospace font, about 0.6 em per character, then a gap.
+fn legend_entry_width(repeat: &str, font_size: i32) -> i32 {
+    25 + (repeat.chars().count() as f64 * font_size as f64 * 0.6).ceil() as i32 + font_size
+}
+
+/// Where each legend entry goes, as its x and its row from the top.
+/// Entries fill a row until it is as wide as the plot, then wrap onto
+/// the next. Each row is right aligned.
+fn legend_layout(repeats: &[String], width: i32, font_size: i32) -> Vec<(i32, usize)> {
+    let available = width - 2 * MARGIN;
+    let mut rows: Vec<Vec<i32>> = Vec::new();
+    for repeat in repeats {
+        let entry_width = legend_entry_width(repeat, font_size);
+        match rows.last_mut() {
+            Some(row) if row.iter().sum::<i32>() + entry_width <= available => {
+                row.push(entry_width)
+            }
+            _ => rows.push(vec![entry_width]),
+        }
+    }
+    let mut layout = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let mut x = width - MARGIN - row.iter().sum::<i32>();
+        for entry_width in row {
+            layout.push((x, i));
+            x += entry_width;
+        }
+    }
+    layout
+}
+
+/// Add the legend, one entry per telomeric repeat.
+fn add_legend(repeats: &[String], width: i32, font_size: i32) -> String {
+    let mut legend = String::new();
+    let layout = legend_layout(repeats, width, font_size);
+    for (i, (repeat, (x, row))) in repeats.iter().zip(layout).enumerate() {
+        let (colour, dash) = series_style(i);
+        let y = MARGIN / 2 + row as i32 * legend_row_height(font_size);
+        legend += &format!(
+            "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='{}' stroke-dasharray='{}' stroke-width='2'/>",
+            x,
+            y,
+            x + 20,
+            y,
+            colour,
+            dash
+        );
+        legend += &format!(
+            "<text x='{}' y='{}' class='legend_label' font-family='monospace' font-size='{}' dominant-baseline='middle'>{}</text>\n",
+            x + 25,
+            y,
+            font_size,
+            escape_xml(repeat)
+        );
+    }
+    legend
+}
+
+/// Add the axes and ticks of a subplot.
+fn add_axes(row: &PlotData, subplot: &Subplot, font_size: i32, y_scale: YScale) -> String {
+    let mut axes = String::new();
+    let tick_length = 4.0;
+
+    // the axis lines
+    axes += &format!(
+        "<path d='M{},{}L{},{}L{},{}' class='axis' stroke='black' fill='none' stroke-width='1'/>",
+        subplot.left,
+        subplot.top,
+        subplot.left,
+        subplot.bottom(),
+        subplot.left + subplot.width,
+        subplot.bottom()
+    );
+
+    // x ticks in genomic coordinates
+    let x_ticks = nice_ticks(row.x_max, 8, 1.0);
+    let x_step = x_ticks.get(1).copied().unwrap_or(row.x_max);
+    for tick in &x_ticks {
+        let x = subplot.left + scale_y(*tick, 0.0, subplot.width, 0.0, row.x_max);
+        axes += &format!(
+            "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='black' stroke-width='1'/>",
+            x,
+            subplot.bottom(),
+            x,
+            subplot.bottom() + tick_length
+        );
+        axes += &format!(
+            "<text x='{}' y='{}' class='x_axis_label' font-family='monospace' font-size='{}' text-anchor='middle' dominant-baseline='hanging'>{}</text>",
+            x,
+            subplot.bottom() + tick_length + 2.0,
+            font_size,
+            format_position(*tick, x_step, row.x_max)
+        );
+    }
+
+    // y ticks in repeat counts
+    for tick in y_scale.ticks(row.y_max) {
+        let y = subplot.bottom()
+            - scale_y(
+                y_scale.transform(tick),
+                0.0,
+                subplot.height,
+                0.0,
+                y_scale.transform(row.y_max),
+            );
+        axes += &format!(
+            "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='black' stroke-width='1'/>",
+            subplot.left - tick_length,
+            y,
+            subplot.left,
+            y
+        );
+        axes += &format!(
+            "<text x='{}' y='{}' class='y_axis_label' font-family='monospace' font-size='{}' text-anchor='end' dominant-baseline='middle'>{}</text>",
+            subplot.left - tick_length - 2.0,
+            y,
+            font_size,
+            // trim the float error of fractional ticks
+            (tick * 1e6).round() / 1e6
+        );
+    }
+    axes += "\n";
+    axes
+}
+
+/// Add the path elements from Vec<PlotData.series> to their SVG tags,
+/// along with the labels and axes of each subplot.
 fn add_all_path_elements(
     plot_data: Vec<PlotData>,
-    height_subplot: isize,
-    width: i32,
-    font_size: i32,
-    stroke_width: i32,
+    repeats: &[String],
+    stacked: &Stacked,
 ) -> String {
-    let mut all_paths = String::new();
-    let length = plot_data.len();
-    // gap between subplots; could be added as a parameter.
-    let subplot_gap = 25;
-    // chromosome labels WRONG as they are being labelled from the top down, rather than the bottom up.
-    for (i, row) in plot_data.iter().enumerate() {
-        // add text tag here
-        all_paths += &format!(
-            "<text x='{}' y='{}' class='chromosome_label' font-family='monospace' font-size='{}' font-weight='bold'>{}\n↓</text>",
-            (MARGIN / 2) + 5,
-            (i as isize * height_subplot) + subplot_gap - 5 + MARGIN as isize,
-            font_size,
-            row.id
-        );
-        // add axis tags here
+    let mut all_paths = add_legend(repeats, stacked.width, stacked.font_size);
+    for row in &plot_data {
+        let subplot = row.subplot;
+        // the chromosome label above the subplot
         all_paths += &format!(
-            "<text x='{}' y='{}' class='x_axis_label' font-family='monospace' font-size='{}' font-weight='bold' text-anchor='end'>{} ↑</text>",
-            width - (MARGIN * 2) + MARGIN / 2,
-            (i as isize * height_subplot) + subplot_gap - 5 + MARGIN as isize + height_subplot,
-            font_size,
-            format_number_to_mb(row.max)
+            "<text x='{}' y='{}' class='chromosome_label' font-family='monospace' font-size='{}' font-weight='bold'>{}</text>",
+            subplot.left,
+            subplot.top - 6.0,
+            stacked.font_size,
+            escape_xml(&row.title)
         );
-        // reverse the order of the paths!
-        all_paths += &format!("<path d='{}' id='{}' class='chromosome_line' stroke='black' fill='none' stroke-width='{}' transform='translate(0,{})'/>\n", 
-                plot_data[length - i - 1].path,
-                plot_data[length - i - 1].id,
-                stroke_width,
-                -(i as isize * height_subplot));
+        all_paths += &add_axes(row, &subplot, stacked.font_size, stacked.y_scale);
+
+        for series in &row.series {
+            // colour by the position of the repeat in the whole file, so
+            // a repeat has the same colour on every chromosome.
+            let n = repeats
+                .iter()
+                .position(|r| r == &series.sequence)
+                .unwrap_or(0);
+            let (colour, dash) = series_style(n);
+            all_paths += &format!("<path d='{}' id='{}_{}' class='chromosome_line' stroke='{}' stroke-dasharray='{}' fill='none' stroke-width='{}'/>\n",
+                series.path,
+                escape_xml(&row.id),
+                escape_xml(&series.sequence),
+                colour,
+                dash,
+                stacked.stroke_width);
+        }
+
+        all_paths += &add_tracks(row, stacked);
     }
     all_paths
 }
 
-/// Format [`usize`] to megabase string.
-fn format_number_to_mb(n: usize) -> String {
-    format!("{:.1}Mb", (n as f64 / 1_000_000_f64))
+/// Draw the annotation tracks under a subplot, below its x tick
+/// labels. Each feature is a bar scaled to the x axis.
+fn add_tracks(row: &PlotData, stacked: &Stacked) -> String {
+    let subplot = row.subplot;
+    let track_height = stacked.track_height() as f64;
+    // below the x tick labels
+    let top = subplot.bottom() + (stacked.font_size + 8) as f64;
+    let to_x = |position: i64| subplot.left + (position as f64 / row.x_max) * subplot.width;
+    let mut tracks = String::new();
+
+    for (i, (name, features)) in row.tracks.iter().enumerate() {
+        let y = top + i as f64 * track_height;
+        // the track name in the gutter of the y tick labels
+        tracks += &format!(
+            "<text x='{}' y='{}' class='track_label' font-family='monospace' font-size='{}' text-anchor='end' dominant-baseline='middle'>{}</text>\n",
+            subplot.left - 4.0,
+            y + track_height / 2.0,
+            stacked.font_size - 2,
+            escape_xml(name)
+        );
+        tracks += &format!(
+            "<line x1='{}' y1='{}' x2='{}' y2='{}' stroke='lightgrey'/>\n",
+            subplot.left,
+            y + track_height / 2.0,
+            subplot.left + subplot.width,
+            y + track_height / 2.0
+        );
+        for feature in features {
+            let x = to_x(feature.start);
+            // at least a pixel wide, so short features are visible
+            let width = (to_x(feature.end) - x).max(1.0);
+            let title = match &feature.name {
+                Some(n) => format!("{} {}:{}-{}", n, feature.id, feature.start, feature.end),
+                None => format!("{}:{}-{}", feature.id, feature.start, feature.end),
+            };
+            tracks += &format!(
+                "<rect x='{}' y='{}' width='{}' height='{}' class='track_feature' fill='{}'><title>{}</title></rect>\n",
+                x,
+                y + 2.0,
+                width,
+                track_height - 4.0,
+                PALETTE[PALETTE.len() - 1 - i % PALETTE.len()],
+                escape_xml(&title)
+            );
+        }
+    }
+    tracks
+}
+
+/// An interval from a BED file.
+#[derive(Debug, Clone, PartialEq)]
+struct Feature {
+    id: String,
+    start: i64,
+    end: i64,
+    /// The name column, if present.
+    name: Option<String>,
+}
+
+/// The features of one BED file, drawn as a track.
+#[derive(Debug, Clone)]
+struct Annotation {
+    /// The track name, or the file name.
+    name: String,
+    features: Vec<Feature>,
+}
+
+/// Parse a BED file of at least three columns into an annotation
+/// track. The name is taken from a track line, if there is one.
+fn parse_bed(path: &Path) -> Result<Annotation> {
+    let reader = BufReader::new(utils::open_file(path)?);
+    let default_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    let mut annotation = parse_bed_features(reader).map_err(|e| {
+        Error::Parse(format!(
+            "Could not parse the BED file {}. {}",
+            path.display(),
+            e
+        ))
+    })?;
+    if annotation.name.is_empty() {
+        annotation.name = default_name;
+    }
+    Ok(annotation)
+}
+
+/// Parse the lines of a BED file. The name is left empty if there
+/// is no track line.
+fn parse_bed_features<R: BufRead>(reader: R) -> Result<Annotation> {
+    let mut name = String::new();
+    let mut features = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let Some(n) = track_name(&line) {
+            name = n;
+        }
+        if is_bedgraph_header(&line) || line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            return Err(Error::Parse(format!(
+                "Line {} has {} columns, expected at least 3.",
+                i + 1,
+                fields.len()
+            )));
+        }
+        let parse_error = || {
+            Error::Parse(format!(
+                "Could not parse the coordinates on line {}.",
+                i + 1
+            ))
+        };
+        features.push(Feature {
+            id: fields[0].to_string(),
+            start: fields[1].trim().parse().map_err(|_| parse_error())?,
+            end: fields[2].trim().parse().map_err(|_| parse_error())?,
+            name: fields
+                .get(3)
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty()),
+        });
+    }
+    Ok(Annotation { name, features })
+}
+
+/// The counts of a single telomeric repeat along a chromosome.
+#[derive(Debug, Clone)]
+pub struct Series {
+    /// The telomeric repeat counted.
+    pub sequence: String,
+    /// SVG path attribute.
+    pub path: String,
 }
 
 /// The final data structure.
@@ -246,107 +1304,1035 @@ fn format_number_to_mb(n: usize) -> String {
 pub struct PlotData {
     /// Chromosome ID.
     pub id: String,
-    /// SVG path attribute.
-    pub path: String,
     /// Max length of chromosome.
     pub max: usize,
-    /// Name of the telomeric repeat (not needed?).
-    pub sequence: String,
+    /// The length the x axis is scaled to, the chromosome length
+    /// unless the axis is shared.
+    pub x_max: f64,
+    /// The count the y axis is scaled to.
+    pub y_max: f64,
+    /// One series for each telomeric repeat in the chromosome.
+    pub series: Vec<Series>,
+    /// The label above the subplot.
+    title: String,
+    /// Where the subplot is drawn.
+    subplot: Subplot,
+    /// The features on this chromosome in each annotation track.
+    tracks: Vec<(String, Vec<Feature>)>,
 }
 
-/// Loop through the parsed TSV file and
-/// calculate SVG path elements on the fly
-/// along with other [`PlotData`] elements.
-fn generate_plot_data(
-    parsed_tsv: Vec<TelomericRepeatRecord>,
-    height: i32,
-    width: i32,
-    height_per_plot: i32,
-) -> Vec<PlotData> {
-    // so we can break the loop
-    let file_length = parsed_tsv.len();
-    // the iteration of the loop
-    let mut it = 0usize;
-    // a mutable vector to calculate svg path attribute
-    let mut path_vec = Vec::new();
-    let mut plot_data = Vec::new();
-    let mut y_max = 0;
+/// The windows of a single chromosome, grouped by telomeric repeat.
+#[derive(Debug)]
+struct ChromosomeWindows {
+    id: String,
+    /// The last window, i.e. the length of the chromosome.
+    max: i64,
+    /// Each telomeric repeat in order of appearance, along with
+    /// its windows.
+    repeats: Vec<(String, Vec<Window>)>,
+}
 
-    loop {
-        if it == file_length - 1 {
-            // there may not be a path element
-            // so explicitly make a blank if there is not.
-            let path_element = match make_path_element(
-                path_vec.clone(),
-                path_vec.clone().len(),
-                y_max as usize,
-                height,
-                width,
-                height_per_plot,
-            ) {
-                Some(x) => x,
-                None => " ".to_owned(),
-            };
+/// A window along a chromosome, with the forward and reverse
+/// counts of a telomeric repeat.
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    start: i64,
+    end: i64,
+    forward: f64,
+    reverse: f64,
+}
 
-            plot_data.push(PlotData {
-                id: parsed_tsv[it].id.clone(),
-                path: path_element,
-                max: parsed_tsv[it].window as usize,
-                sequence: parsed_tsv[it].telomeric_repeat.clone(),
+impl Window {
+    /// The forward + reverse count.
+    fn count(&self) -> f64 {
+        self.forward + self.reverse
+    }
+}
+
+/// A single window from either input format, before grouping.
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    id: String,
+    /// The telomeric repeat, or the track name of a bedGraph.
+    repeat: String,
+    window: Window,
+}
+
+/// Convert the TSV records to rows. The TSV only records the end
+/// of a window, so the start is the end of the previous window of
+/// the same chromosome and telomeric repeat.
+fn tsv_rows(parsed_tsv: Vec<TelomericRepeatRecord>) -> Vec<Row> {
+    let mut previous_ends: HashMap<(String, String), i64> = HashMap::new();
+    parsed_tsv
+        .into_iter()
+        .map(|record| {
+            let previous_end = previous_ends
+                .entry((record.id.clone(), record.telomeric_repeat.clone()))
+                .or_insert(0);
+            // the first version of the TSV only has the end of each
+            // window, so the start is the end of the last window
+            let end = record.end.or(record.window).unwrap_or(*previous_end);
+            let start = record.start.unwrap_or(*previous_end);
+            *previous_end = end;
+            Row {
+                id: record.id,
+                repeat: record.telomeric_repeat,
+                window: Window {
+                    start,
+                    end,
+                    forward: record.forward_repeat_number as f64,
+                    reverse: record.reverse_repeat_number as f64,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Convert the parsed TSV into rows of repeat density, rather than
+/// counts. Windows which are all N have no density, so are left out.
+fn density_rows(parsed_tsv: Vec<TelomericRepeatRecord>) -> Result<Vec<Row>> {
+    if parsed_tsv
+        .iter()
+        .all(|record| record.repeat_density.is_none())
+    {
+        return Err(Error::Parse(
+            "The TSV has no repeat densities. Rerun `tidk search` or `tidk find` to add them."
+                .to_string(),
+        ));
+    }
+    let densities: Vec<Option<f64>> = parsed_tsv
+        .iter()
+        .map(|record| record.repeat_density)
+        .collect();
+    Ok(tsv_rows(parsed_tsv)
+        .into_iter()
+        .zip(densities)
+        .filter_map(|(mut row, density)| {
+            row.window.forward = density?;
+            row.window.reverse = 0.0;
+            Some(row)
+        })
+        .collect())
+}
+
+/// Group the rows by chromosome ID, and within each chromosome
+/// by telomeric repeat. `tidk find` writes one row per window for each
+/// repeat of a clade, so an ID may appear several times.
+fn group_by_chromosome(rows: Vec<Row>) -> Vec<ChromosomeWindows> {
+    let mut chromosomes: Vec<ChromosomeWindows> = Vec::new();
+    // index into `chromosomes` by ID
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for row in rows {
+        let index = *positions.entry(row.id.clone()).or_insert_with(|| {
+            chromosomes.push(ChromosomeWindows {
+                id: row.id.clone(),
+                max: 0,
+                repeats: Vec::new(),
             });
-            break;
+            chromosomes.len() - 1
+        });
+        let chromosome = &mut chromosomes[index];
+        chromosome.max = chromosome.max.max(row.window.end);
+
+        match chromosome
+            .repeats
+            .iter_mut()
+            .find(|(repeat, _)| repeat == &row.repeat)
+        {
+            Some((_, windows)) => windows.push(row.window),
+            None => chromosome.repeats.push((row.repeat, vec![row.window])),
         }
+    }
+    chromosomes
+}
 
-        if parsed_tsv[it].id == parsed_tsv[it + 1].id {
-            // calculate y max
-            if y_max <= parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number
-            {
-                y_max = parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number;
+/// All the distinct telomeric repeats in the grouped TSV, in
+/// order of appearance.
+fn distinct_repeats<'a>(
+    chromosomes: impl IntoIterator<Item = &'a ChromosomeWindows>,
+) -> Vec<String> {
+    let mut repeats: Vec<String> = Vec::new();
+    for chromosome in chromosomes {
+        for (repeat, _) in &chromosome.repeats {
+            if !repeats.contains(repeat) {
+                repeats.push(repeat.clone());
             }
-            // window (i.e x)
-            // forward + reverse counts
-            path_vec.push((
-                parsed_tsv[it].window.try_into().unwrap(),
-                parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number,
-            ));
-            it += 1;
-        } else {
-            // want to calculate y_max and...
-            if y_max <= parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number
-            {
-                y_max = parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number;
+        }
+    }
+    repeats
+}
+
+/// Rename chromosome IDs with a mapping, so that matching chromosomes
+/// in different genomes share an ID. IDs not in the mapping are kept.
+fn rename_ids(rows: &mut [Row], id_map: &HashMap<String, String>) {
+    for row in rows {
+        if let Some(id) = id_map.get(&row.id) {
+            row.id = id.clone();
+        }
+    }
+}
+
+/// Parse a two column TSV mapping chromosome IDs to shared IDs.
+fn parse_id_map(path: &Path) -> Result<HashMap<String, String>> {
+    let reader = BufReader::new(utils::open_file(path)?);
+    let mut id_map = HashMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        match (fields.next(), fields.next()) {
+            (Some(from), Some(to)) => {
+                id_map.insert(from.trim().to_string(), to.trim().to_string());
             }
-            // the path vector for the last element (seems important for things which occur at the
-            // ends of chromosomes right..? DOH)
-            path_vec.push((
-                parsed_tsv[it].window.try_into().unwrap(),
-                parsed_tsv[it].forward_repeat_number + parsed_tsv[it].reverse_repeat_number,
-            ));
-            // calculate the svg path element from path_vec here
-            // there may not be a path element
-            // so explicitly make a blank if there is not.
-            let path_element = match make_path_element(
-                path_vec.clone(),
-                path_vec.clone().len(),
-                y_max as usize,
-                height,
-                width,
-                height_per_plot,
-            ) {
-                Some(x) => x,
-                None => " ".to_owned(),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "Line {} of {} should have two tab separated columns.",
+                    i + 1,
+                    path.display()
+                )))
+            }
+        }
+    }
+    Ok(id_map)
+}
+
+/// Overlay several genomes, prefixing each telomeric repeat with the
+/// label of its genome so each genome is its own series.
+fn overlay(genomes: Vec<Vec<Row>>, labels: &[String]) -> Vec<Row> {
+    genomes
+        .into_iter()
+        .zip(labels)
+        .flat_map(|(rows, label)| {
+            rows.into_iter().map(move |mut row| {
+                row.repeat = format!("{} {}", label, row.repeat);
+                row
+            })
+        })
+        .collect()
+}
+
+/// Match chromosomes by ID across genomes. Each row of the grid is a
+/// chromosome, in order of first appearance, with a column for each
+/// genome, which is `None` if that genome lacks the chromosome.
+fn comparison_grid(genomes: Vec<Vec<ChromosomeWindows>>) -> Vec<Vec<Option<ChromosomeWindows>>> {
+    let columns = genomes.len();
+    let mut grid: Vec<Vec<Option<ChromosomeWindows>>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for (column, chromosomes) in genomes.into_iter().enumerate() {
+        for chromosome in chromosomes {
+            let index = *positions.entry(chromosome.id.clone()).or_insert_with(|| {
+                grid.push((0..columns).map(|_| None).collect());
+                grid.len() - 1
+            });
+            grid[index][column] = Some(chromosome);
+        }
+    }
+    grid
+}
+
+/// Flatten a comparison grid into a single column, labelling each
+/// chromosome with its genome.
+fn interleave_genomes(
+    grid: Vec<Vec<Option<ChromosomeWindows>>>,
+    labels: &[String],
+) -> Vec<ChromosomeWindows> {
+    grid.into_iter()
+        .flat_map(|row| {
+            row.into_iter()
+                .zip(labels)
+                .filter_map(|(chromosome, label)| {
+                    chromosome.map(|mut c| {
+                        c.id = format!("{} ({})", c.id, label);
+                        c
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Loop through the grouped TSV and calculate SVG
+/// path elements for each telomeric repeat, along
+/// with other [`PlotData`] elements.
+#[cfg(test)]
+fn generate_plot_data(chromosomes: Vec<ChromosomeWindows>, stacked: &Stacked) -> Vec<PlotData> {
+    let grid = chromosomes.into_iter().map(|c| vec![Some(c)]).collect();
+    generate_comparison_plot_data(grid, &[], &[], stacked)
+}
+
+/// Loop through the comparison grid and calculate SVG path elements
+/// for each telomeric repeat, along with other [`PlotData`] elements.
+/// With a single genome, the grid has a single column. Missing
+/// chromosomes get an empty subplot.
+fn generate_comparison_plot_data(
+    grid: Vec<Vec<Option<ChromosomeWindows>>>,
+    labels: &[String],
+    annotations: &[Annotation],
+    stacked: &Stacked,
+) -> Vec<PlotData> {
+    let chromosomes = || grid.iter().flatten().flatten();
+    let genome_max = chromosomes().map(|c| c.max).max().unwrap_or(0);
+    let genome_y_max = chromosomes().map(|c| c.count_max()).fold(0.0, f64::max);
+    let mut plot_data = Vec::new();
+
+    for (i, row) in grid.iter().enumerate() {
+        // the axes of a chromosome are shared between genomes, so
+        // they can be compared
+        let row_max = row.iter().flatten().map(|c| c.max).max().unwrap_or(0);
+        let row_y_max = row
+            .iter()
+            .flatten()
+            .map(|c| c.count_max())
+            .fold(0.0, f64::max);
+        let x_max = if stacked.shared_x {
+            genome_max
+        } else {
+            row_max
+        }
+        .max(1) as f64;
+        // y is scaled to the greatest count of any repeat on this
+        // chromosome (or genome), so the series within a subplot are
+        // comparable.
+        let y_max = if stacked.shared_y {
+            genome_y_max
+        } else {
+            row_y_max
+        }
+        .max(1.0);
+        let id = row
+            .iter()
+            .flatten()
+            .map(|c| c.id.clone())
+            .next()
+            .unwrap_or_default();
+
+        for (column, chromosome) in row.iter().enumerate() {
+            let subplot = stacked.subplot(i, column);
+            let title = match (labels.get(column), chromosome) {
+                (Some(label), Some(_)) if row.len() > 1 => format!("{} ({})", id, label),
+                (Some(label), None) => format!("{} ({}): absent", id, label),
+                _ => id.clone(),
             };
 
+            let mut series = Vec::new();
+            for (sequence, windows) in chromosome.iter().flat_map(|c| c.repeats.iter()) {
+                // there may not be a path element
+                // so explicitly make a blank if there is not.
+                let path = match make_path_element(windows, x_max, y_max, stacked.y_scale, &subplot)
+                {
+                    Some(x) => x,
+                    None => " ".to_owned(),
+                };
+                series.push(Series {
+                    sequence: sequence.clone(),
+                    path,
+                });
+            }
+
             plot_data.push(PlotData {
-                id: parsed_tsv[it].id.clone(),
-                path: path_element,
-                max: parsed_tsv[it].window as usize,
-                sequence: parsed_tsv[it].telomeric_repeat.clone(),
+                id: id.clone(),
+                max: chromosome.as_ref().map(|c| c.max).unwrap_or(0) as usize,
+                x_max,
+                y_max,
+                series,
+                title,
+                subplot,
+                tracks: annotations
+                    .iter()
+                    .map(|annotation| {
+                        let features = match chromosome {
+                            Some(_) => annotation
+                                .features
+                                .iter()
+                                .filter(|f| f.id == id)
+                                .cloned()
+                                .collect(),
+                            None => Vec::new(),
+                        };
+                        (annotation.name.clone(), features)
+                    })
+                    .collect(),
             });
-            path_vec.clear();
-            it += 1;
-            y_max = 0;
         }
     }
     plot_data
 }
+
+/// The proportion of a terminal window which must be covered by
+/// telomeric repeats for an array to be called at that chromosome end.
+const ARRAY_CALL_PROPORTION: f64 = 0.1;
+
+/// A window of a chromosome, summed over all telomeric repeats.
+#[derive(Debug, PartialEq)]
+struct MergedWindow {
+    start: i64,
+    end: i64,
+    /// Forward + reverse count of all repeats.
+    count: f64,
+    /// The number of bases covered by the repeats, if the windows
+    /// hold repeat counts.
+    repeat_bases: Option<f64>,
+}
+
+impl MergedWindow {
+    /// Is this window covered enough by telomeric repeats to
+    /// call a telomeric array?
+    fn is_array(&self) -> bool {
+        let length = self.end - self.start;
+        length > 0
+            && self
+                .repeat_bases
+                .is_some_and(|bases| bases / length as f64 >= ARRAY_CALL_PROPORTION)
+    }
+}
+
+impl ChromosomeWindows {
+    /// The greatest count of any telomeric repeat in a window.
+    fn count_max(&self) -> f64 {
+        self.repeats
+            .iter()
+            .flat_map(|(_, windows)| windows.iter().map(|w| w.count()))
+            .fold(0.0, f64::max)
+    }
+
+    /// Sum the counts of every telomeric repeat in each window. The
+    /// bases covered by the repeats are only known when the windows
+    /// hold `repeat_counts`, rather than a density or bedGraph values.
+    fn merged_windows(&self, repeat_counts: bool) -> Vec<MergedWindow> {
+        let mut by_window: BTreeMap<(i64, i64), (f64, f64)> = BTreeMap::new();
+        for (repeat, windows) in &self.repeats {
+            for window in windows {
+                let entry = by_window
+                    .entry((window.start, window.end))
+                    .or_insert((0.0, 0.0));
+                entry.0 += window.count();
+                entry.1 += window.count() * repeat.len() as f64;
+            }
+        }
+
+        by_window
+            .into_iter()
+            .map(|((start, end), (count, repeat_bases))| MergedWindow {
+                start,
+                end,
+                count,
+                repeat_bases: repeat_counts.then_some(repeat_bases),
+            })
+            .collect()
+    }
+}
+
+/// Map a proportion in [0, 1] onto a white-yellow-red heat scale.
+fn heat_colour(t: f64) -> String {
+    // yellow, orange, red
+    let stops = [
+        (255.0, 255.0, 204.0),
+        (253.0, 141.0, 60.0),
+        (189.0, 0.0, 38.0),
+    ];
+    let t = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (t.floor() as usize).min(stops.len() - 2);
+    let f = t - i as f64;
+    let (r0, g0, b0) = stops[i];
+    let (r1, g1, b1) = stops[i + 1];
+    format!(
+        "rgb({},{},{})",
+        (r0 + (r1 - r0) * f).round(),
+        (g0 + (g1 - g0) * f).round(),
+        (b0 + (b1 - b0) * f).round()
+    )
+}
+
+/// A genome-wide overview. Each chromosome is a horizontal bar
+/// scaled to its length, coloured by telomeric repeat density, with
+/// a marker above each end where a telomeric array is called. Arrays
+/// are only called from `repeat_counts`.
+///
+/// Returns the height of the plot along with the SVG elements.
+fn karyotype(
+    chromosomes: &[ChromosomeWindows],
+    width: i32,
+    font_size: i32,
+    y_scale: YScale,
+    repeat_counts: bool,
+) -> (i32, String) {
+    let bar_height = font_size * 2;
+    let row_height = font_size * 3;
+    // room for the colour scale
+    let top = MARGIN + font_size * 2;
+    let height = top + row_height * chromosomes.len() as i32 + MARGIN;
+
+    // monospace characters are roughly 0.6em wide
+    let longest_id = chromosomes.iter().map(|c| c.id.len()).max().unwrap_or(0);
+    let label_width = (longest_id as f64 * font_size as f64 * 0.6) as i32 + 10;
+    let bar_start = MARGIN + label_width;
+    // leave space for the length label on the right
+    let bar_width = (width - bar_start - MARGIN - font_size * 4).max(1) as f64;
+
+    let genome_max = chromosomes.iter().map(|c| c.max).max().unwrap_or(1).max(1) as f64;
+    let merged: Vec<Vec<MergedWindow>> = chromosomes
+        .iter()
+        .map(|c| c.merged_windows(repeat_counts))
+        .collect();
+    let count_max = merged
+        .iter()
+        .flatten()
+        .map(|w| w.count)
+        .fold(0.0, f64::max)
+        .max(1.0);
+
+    let mut elements = String::new();
+
+    // the colour scale
+    let scale_width = 150;
+    let scale_label = match y_scale {
+        YScale::Linear => format!("{} repeats/window", count_max),
+        YScale::Log => format!("{} repeats/window (log)", count_max),
+    };
+    let scale_label_width = (scale_label.len() as f64 * font_size as f64 * 0.6) as i32;
+    let scale_x = width - MARGIN - scale_label_width - 5 - scale_width;
+    elements += &format!(
+        "<defs><linearGradient id='heat'><stop offset='0' stop-color='{}'/><stop offset='0.5' stop-color='{}'/><stop offset='1' stop-color='{}'/></linearGradient></defs>",
+        heat_colour(0.0),
+        heat_colour(0.5),
+        heat_colour(1.0)
+    );
+    elements += &format!(
+        "<rect x='{}' y='{}' width='{}' height='{}' fill='url(#heat)' stroke='black' stroke-width='0.5'/>\n",
+        scale_x,
+        MARGIN / 2,
+        scale_width,
+        font_size
+    );
+    elements += &format!(
+        "<text x='{}' y='{}' class='legend_label' font-family='monospace' font-size='{}' text-anchor='end' dominant-baseline='middle'>0</text>",
+        scale_x - 5,
+        MARGIN / 2 + font_size / 2,
+        font_size
+    );
+    elements += &format!(
+        "<text x='{}' y='{}' class='legend_label' font-family='monospace' font-size='{}' dominant-baseline='middle'>{}</text>\n",
+        scale_x + scale_width + 5,
+        MARGIN / 2 + font_size / 2,
+        font_size,
+        scale_label
+    );
+
+    for (i, (chromosome, windows)) in chromosomes.iter().zip(merged.iter()).enumerate() {
+        let y = top + i as i32 * row_height;
+        let scale_x = |position: i64| bar_start as f64 + position as f64 / genome_max * bar_width;
+        let bar_end = scale_x(chromosome.max);
+
+        elements += &format!(
+            "<text x='{}' y='{}' class='chromosome_label' font-family='monospace' font-size='{}' font-weight='bold' dominant-baseline='middle'>{}</text>",
+            MARGIN,
+            y + bar_height / 2,
+            font_size,
+            escape_xml(&chromosome.id)
+        );
+        // the chromosome itself
+        elements += &format!(
+            "<rect x='{}' y='{}' width='{}' height='{}' fill='{}'/>",
+            bar_start,
+            y,
+            bar_end - bar_start as f64,
+            bar_height,
+            heat_colour(0.0)
+        );
+        // the windows with any repeats
+        for window in windows.iter().filter(|w| w.count > 0.0) {
+            let x = scale_x(window.start);
+            elements += &format!(
+                "<rect x='{}' y='{}' width='{}' height='{}' class='karyotype_window' fill='{}'><title>{}:{}-{} {}</title></rect>",
+                x,
+                y,
+                (scale_x(window.end) - x).max(0.5),
+                bar_height,
+                heat_colour(
+                    y_scale.transform(window.count) / y_scale.transform(count_max)
+                ),
+                escape_xml(&chromosome.id),
+                window.start,
+                window.end,
+                window.count
+            );
+        }
+        // outline drawn last so it sits on top of the windows
+        elements += &format!(
+            "<rect x='{}' y='{}' width='{}' height='{}' fill='none' stroke='black' stroke-width='1'/>",
+            bar_start,
+            y,
+            bar_end - bar_start as f64,
+            bar_height
+        );
+        elements += &format!(
+            "<text x='{}' y='{}' class='x_axis_label' font-family='monospace' font-size='{}' dominant-baseline='middle'>{}</text>\n",
+            bar_end + 5.0,
+            y + bar_height / 2,
+            font_size,
+            format_position(
+                chromosome.max as f64,
+                chromosome.max as f64 / 100.0,
+                chromosome.max as f64
+            )
+        );
+
+        // markers at the ends with telomeric arrays
+        let ends = [
+            (windows.first(), bar_start as f64),
+            (windows.last(), bar_end),
+        ];
+        for (window, x) in ends {
+            if window.is_some_and(|w| w.is_array()) {
+                elements += &format!(
+                    "<path d='M{},{}L{},{}L{},{}Z' class='array_marker' fill='dodgerblue'/>\n",
+                    x - 5.0,
+                    y - 8,
+                    x + 5.0,
+                    y - 8,
+                    x,
+                    y - 1
+                );
+            }
+        }
+    }
+
+    (height, elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, window: i64, forward: i32, repeat: &str) -> TelomericRepeatRecord {
+        TelomericRepeatRecord {
+            id: id.into(),
+            window: Some(window),
+            start: None,
+            end: None,
+            forward_repeat_number: forward,
+            reverse_repeat_number: 0,
+            telomeric_repeat: repeat.into(),
+            n_count: None,
+            masked_count: None,
+            gc_content: None,
+            repeat_density: None,
+        }
+    }
+
+    // `tidk find` output for a clade with two telomeric repeats
+    fn multi_motif_tsv() -> Vec<TelomericRepeatRecord> {
+        vec![
+            record("chr1", 10, 5, "TTAGG"),
+            record("chr1", 20, 0, "TTAGG"),
+            record("chr1", 10, 1, "TTAGGG"),
+            record("chr1", 20, 7, "TTAGGG"),
+            record("chr2", 10, 2, "TTAGG"),
+            record("chr2", 15, 3, "TTAGG"),
+            record("chr2", 10, 0, "TTAGGG"),
+            record("chr2", 15, 0, "TTAGGG"),
+        ]
+    }
+
+    #[test]
+    fn test_group_by_chromosome() {
+        let grouped = group_by_chromosome(tsv_rows(multi_motif_tsv()));
+
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].id, "chr1");
+        assert_eq!(grouped[0].max, 20);
+        assert_eq!(grouped[0].repeats.len(), 2);
+        assert_eq!(grouped[0].repeats[1].0, "TTAGGG");
+        assert_eq!(
+            grouped[0].repeats[1].1,
+            vec![
+                Window {
+                    start: 0,
+                    end: 10,
+                    forward: 1.0,
+                    reverse: 0.0
+                },
+                Window {
+                    start: 10,
+                    end: 20,
+                    forward: 7.0,
+                    reverse: 0.0
+                }
+            ]
+        );
+        assert_eq!(grouped[1].max, 15);
+    }
+
+    #[test]
+    fn test_distinct_repeats() {
+        let grouped = group_by_chromosome(tsv_rows(multi_motif_tsv()));
+        assert_eq!(distinct_repeats(&grouped), vec!["TTAGG", "TTAGGG"]);
+    }
+
+    #[test]
+    fn test_series_style_cycles() {
+        assert_eq!(series_style(0), ("black", "none"));
+        assert_eq!(series_style(PALETTE.len()), ("black", "6,3"));
+    }
+
+    #[test]
+    fn test_merged_windows() {
+        let grouped = group_by_chromosome(tsv_rows(multi_motif_tsv()));
+        let merged = grouped[0].merged_windows(true);
+
+        assert_eq!(
+            merged,
+            vec![
+                MergedWindow {
+                    start: 0,
+                    end: 10,
+                    count: 6.0,
+                    repeat_bases: Some(31.0)
+                },
+                MergedWindow {
+                    start: 10,
+                    end: 20,
+                    count: 7.0,
+                    repeat_bases: Some(42.0)
+                }
+            ]
+        );
+        assert!(merged[0].is_array());
+
+        // without repeat counts, no array is called
+        let merged = grouped[0].merged_windows(false);
+        assert_eq!(merged[0].repeat_bases, None);
+        assert!(!merged[0].is_array());
+    }
+
+    #[test]
+    fn test_heat_colour_ends() {
+        assert_eq!(heat_colour(0.0), "rgb(255,255,204)");
+        assert_eq!(heat_colour(1.0), "rgb(189,0,38)");
+    }
+
+    #[test]
+    fn test_nice_ticks() {
+        assert_eq!(
+            nice_ticks(10.0, 5, 1.0),
+            vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+        assert_eq!(nice_ticks(3.0, 5, 1.0), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(
+            nice_ticks(2_300_000.0, 4, 1.0),
+            vec![0.0, 1_000_000.0, 2_000_000.0]
+        );
+    }
+
+    #[test]
+    fn test_format_position() {
+        assert_eq!(format_position(500.0, 100.0, 900.0), "500bp");
+        assert_eq!(format_position(20_000.0, 10_000.0, 57_000.0), "20kb");
+        assert_eq!(
+            format_position(1_500_000.0, 500_000.0, 2_300_000.0),
+            "1.5Mb"
+        );
+    }
+
+    #[test]
+    fn test_legend_layout() {
+        let repeats: Vec<String> = (0..12).map(|i| "TTAGGG".repeat(i % 3 + 1)).collect();
+        let layout = legend_layout(&repeats, 1000, 12);
+        assert_eq!(layout.len(), 12);
+        // wrapped onto more than one row, none off the left edge
+        assert!(layout.last().unwrap().1 > 0);
+        assert!(layout.iter().all(|(x, _)| *x >= MARGIN));
+        // each row ends at the right margin
+        for (i, (repeat, (x, row))) in repeats.iter().zip(&layout).enumerate() {
+            if layout.get(i + 1).is_none_or(|(_, next)| next != row) {
+                assert_eq!(x + legend_entry_width(repeat, 12), 1000 - MARGIN);
+            }
+        }
+        // a short legend is a single row
+        let layout = legend_layout(&repeats[..2], 1000, 12);
+        assert!(layout.iter().all(|(_, row)| *row == 0));
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("chr1"), "chr1");
+        assert_eq!(escape_xml("a<b>&'c\""), "a&lt;b&gt;&amp;&apos;c&quot;");
+
+        // ids and repeats which aren't valid XML still give a valid SVG
+        let mut rows = tsv_rows(multi_motif_tsv());
+        for row in &mut rows {
+            row.id = format!("{}<&'", row.id);
+            row.repeat = format!("{}\"", row.repeat);
+        }
+        let grouped = group_by_chromosome(rows);
+        let repeats = distinct_repeats(&grouped);
+        let (height, body) = karyotype(&grouped, 800, 12, YScale::Linear, true);
+        let body = body + &add_legend(&repeats, 800, 12);
+        assert!(parse_svg(&svg_document(800, height, &body)).is_ok());
+    }
+
+    #[test]
+    fn test_log_scale() {
+        assert_eq!(YScale::Log.transform(0.0), 0.0);
+        assert_eq!(YScale::Log.transform(9.0), 1.0);
+        assert_eq!(YScale::Log.ticks(250.0), vec![0.0, 1.0, 10.0, 100.0]);
+    }
+
+    #[test]
+    fn test_render_png_and_pdf() {
+        let stacked = Stacked {
+            height_subplot: 200,
+            width: 400,
+            font_size: 12,
+            stroke_width: 2,
+            shared_x: false,
+            shared_y: false,
+            y_scale: YScale::Linear,
+            columns: 1,
+            tracks: 0,
+            legend_rows: 1,
+        };
+        let grouped = group_by_chromosome(tsv_rows(multi_motif_tsv()));
+        let repeats = distinct_repeats(&grouped);
+        let height = stacked.height(grouped.len());
+        let body = add_all_path_elements(generate_plot_data(grouped, &stacked), &repeats, &stacked);
+        let svg = svg_document(stacked.width, height, &body);
+
+        let png = render_png(&svg, 192.0).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        // twice the width at 192 DPI, big endian in the IHDR chunk
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 800);
+
+        let pdf = render_pdf(&svg).unwrap();
+        assert_eq!(&pdf[0..5], b"%PDF-");
+    }
+
+    #[test]
+    fn test_html_document() {
+        let grouped = group_by_chromosome(tsv_rows(multi_motif_tsv()));
+        let repeats = distinct_repeats(&grouped);
+        let options = HtmlOptions {
+            width: 1000,
+            height_subplot: 200,
+            stroke_width: 2,
+            shared_x: false,
+            shared_y: false,
+            y_scale: YScale::Linear,
+        };
+        let html = html_document(&grouped, &repeats, &options).unwrap();
+
+        assert!(!html.contains("/*TIDK_DATA*/"));
+        // the second repeat of chr1, with its forward and reverse counts
+        assert!(html.contains(
+            r#"{"sequence":"TTAGGG","style":1,"windows":[[0,10,1.0,0.0],[10,20,7.0,0.0]]}"#
+        ));
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Layout::from_name("karyotype").unwrap(), Layout::Karyotype);
+        assert_eq!(PlotFormat::from_name("pdf").unwrap(), PlotFormat::Pdf);
+        assert!(matches!(
+            Layout::from_name("karyotpe"),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(Compare::from_name("sidebyside").is_err());
+        assert!(YScale::from_name("log2").is_err());
+    }
+
+    #[test]
+    fn test_plot_option_errors() {
+        // checked before any file is read
+        let config = PlotConfig::new(vec![PathBuf::from("missing.tsv")])
+            .beds(vec![PathBuf::from("missing.bed")]);
+        for config in [
+            config.clone().format(PlotFormat::Html),
+            config.clone().layout(Layout::Karyotype),
+            config
+                .beds(Vec::new())
+                .format(PlotFormat::Html)
+                .layout(Layout::Karyotype),
+            PlotConfig::new(vec![PathBuf::from("missing.tsv")])
+                .labels(Some(vec!["a".to_string(), "b".to_string()])),
+        ] {
+            assert!(matches!(plot(&config), Err(Error::InvalidOption(_))));
+        }
+    }
+
+    #[test]
+    fn test_track_name() {
+        assert_eq!(
+            track_name("track type=bedGraph name=\"my track\" visibility=full"),
+            Some("my track".to_string())
+        );
+        assert_eq!(
+            track_name("track name='gaps' description=\"name=x\""),
+            Some("gaps".to_string())
+        );
+        assert_eq!(
+            track_name("track description=\"a name=x\" name=TTAGGG"),
+            Some("TTAGGG".to_string())
+        );
+        assert_eq!(track_name("track type=bedGraph"), None);
+        assert_eq!(track_name("chr1\t0\t10\t1"), None);
+
+        let bed = "track name=\"assembly gaps\"\nchr1\t0\t10\n";
+        assert_eq!(
+            parse_bed_features(bed.as_bytes()).unwrap().name,
+            "assembly gaps"
+        );
+    }
+
+    #[test]
+    fn test_parse_bedgraph() {
+        let bedgraph = "track type=bedGraph name=\"TTAGGG\"\nchr1\t0\t20\t3\nchr1\t20\t40\t0.5\n";
+        let rows = parse_bedgraph(bedgraph.as_bytes()).unwrap();
+
+        assert_eq!(
+            rows[1],
+            Row {
+                id: "chr1".into(),
+                repeat: "TTAGGG".into(),
+                window: Window {
+                    start: 20,
+                    end: 40,
+                    forward: 0.5,
+                    reverse: 0.0
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let tsv = dir.path().join("detect.tsv");
+        std::fs::write(
+            &tsv,
+            "id\twindow\tforward_repeat_number\treverse_repeat_number\ttelomeric_repeat\n",
+        )
+        .unwrap();
+        let bedgraph = dir.path().join("detect.bedgraph");
+        std::fs::write(&bedgraph, "# comment\nchr1\t0\t20\t3\n").unwrap();
+
+        assert_eq!(detect_format(&tsv).unwrap(), InputFormat::Tsv);
+        assert_eq!(detect_format(&bedgraph).unwrap(), InputFormat::BedGraph);
+    }
+
+    #[test]
+    fn test_parse_tsv_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let v1 = dir.path().join("v1.tsv");
+        std::fs::write(
+            &v1,
+            "id\twindow\tforward_repeat_number\treverse_repeat_number\ttelomeric_repeat\n\
+             chr1\t20\t3\t0\tTTAGG\nchr1\t25\t1\t0\tTTAGG\n",
+        )
+        .unwrap();
+        let v4 = dir.path().join("v4.tsv");
+        let mut contents = Vec::new();
+        crate::windows::write_tsv_header(&mut contents).unwrap();
+        contents.extend(
+            b"chr1\t0\t20\t20\t3\t0\tTTAGG\t0\t0\t0.4000\t0.7500\t1\t0\nchr1\t20\t25\t5\t1\t0\tTTAGG\t5\t0\t\t\t\t\n",
+        );
+        std::fs::write(&v4, contents).unwrap();
+
+        let windows = |path: &Path| -> Vec<(i64, i64, f64)> {
+            assert_eq!(detect_format(path).unwrap(), InputFormat::Tsv);
+            parse_input(path, false)
+                .unwrap()
+                .iter()
+                .map(|row| (row.window.start, row.window.end, row.window.forward))
+                .collect()
+        };
+        assert_eq!(windows(&v1), vec![(0, 20, 3.0), (20, 25, 1.0)]);
+        assert_eq!(windows(&v1), windows(&v4));
+
+        let records = parse_tsv(v4.clone()).unwrap();
+        assert_eq!(records[0].gc_content, Some(0.4));
+        // an all N window has no GC content
+        assert_eq!(records[1].n_count, Some(5));
+        assert_eq!(records[1].gc_content, None);
+        assert_eq!(records[1].repeat_density, None);
+
+        // only the window with a density is kept
+        let densities: Vec<f64> = parse_input(&v4, true)
+            .unwrap()
+            .iter()
+            .map(|row| row.window.count())
+            .collect();
+        assert_eq!(densities, vec![0.75]);
+        assert!(parse_input(&v1, true).is_err());
+    }
+
+    #[test]
+    fn test_comparison_grid() {
+        let primary = tsv_rows(multi_motif_tsv());
+        // the alternate haplotype lacks chr1
+        let alternate: Vec<Row> = primary
+            .iter()
+            .filter(|row| row.id == "chr2")
+            .cloned()
+            .collect();
+        let grid = comparison_grid(vec![
+            group_by_chromosome(primary),
+            group_by_chromosome(alternate),
+        ]);
+
+        assert_eq!(grid.len(), 2);
+        assert!(grid[0][1].is_none());
+        assert_eq!(grid[1][1].as_ref().unwrap().id, "chr2");
+
+        let labels = vec!["primary".to_string(), "alternate".to_string()];
+        let ids: Vec<String> = interleave_genomes(grid, &labels)
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["chr1 (primary)", "chr2 (primary)", "chr2 (alternate)"]
+        );
+    }
+
+    #[test]
+    fn test_overlay_and_rename() {
+        let mut rows = tsv_rows(multi_motif_tsv());
+        let id_map = HashMap::from([("chr1".to_string(), "SUPER_1".to_string())]);
+        rename_ids(&mut rows, &id_map);
+        let overlaid = overlay(vec![rows], &["old".to_string()]);
+
+        assert_eq!(overlaid[0].id, "SUPER_1");
+        assert_eq!(overlaid[0].repeat, "old TTAGG");
+        assert_eq!(overlaid[4].id, "chr2");
+    }
+
+    #[test]
+    fn test_parse_bed() {
+        let bed = "track name=\"gaps\"\nchr1\t50000\t52000\tgap\nchr2\t0\t10\n";
+        let annotation = parse_bed_features(bed.as_bytes()).unwrap();
+
+        assert_eq!(annotation.name, "gaps");
+        assert_eq!(
+            annotation.features,
+            vec![
+                Feature {
+                    id: "chr1".into(),
+                    start: 50000,
+                    end: 52000,
+                    name: Some("gap".into())
+                },
+                Feature {
+                    id: "chr2".into(),
+                    start: 0,
+                    end: 10,
+                    name: None
+                },
+            ]
+        );
+        assert!(parse_bed_features("chr1\t1\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    error::check_motif,
+    explore::{Exploration, ExploreConfig},
+    search::SearchConfig,
+    utils,
+    windows::{SoftMask, WindowCount},
+    Error,
+};
+use numpy::IntoPyArray;
+use pyo3::{
+    exceptions::{PyOSError, PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+use std::path::PathBuf;
+
+/// Bad input is a `ValueError`, and files which can't be read an
+/// `OSError`, as Python users would expect.
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(_) => PyOSError::new_err(e.to_string()),
+            Error::Database(_) | Error::Plot(_) | Error::BigWig(_) => {
+                PyRuntimeError::new_err(e.to_string())
+            }
+            _ => PyValueError::new_err(e.to_string()),
+        }
+    }
+}
+
+/// The window counts of `search` by column.
+#[derive(Debug, Default, PartialEq)]
+struct WindowColumns {
+    id: Vec<String>,
+    start: Vec<u64>,
+    end: Vec<u64>,
+    forward: Vec<u64>,
+    reverse: Vec<u64>,
+    /// Only when soft-masked repeats are counted separately.
+    masked_forward: Vec<u64>,
+    masked_reverse: Vec<u64>,
+    n_count: Vec<u64>,
+    gc_content: Vec<f64>,
+    repeat_density: Vec<f64>,
+}
+
+impl WindowColumns {
+    fn push(&mut self, window: WindowCount) {
+        self.start.push(window.start as u64);
+        self.end.push(window.end as u64);
+        self.forward.push(window.forward as u64);
+        self.reverse.push(window.reverse as u64);
+        if let (Some(forward), Some(reverse)) = (window.masked_forward, window.masked_reverse) {
+            self.masked_forward.push(forward as u64);
+            self.masked_reverse.push(reverse as u64);
+        }
+        self.n_count.push(window.n_count as u64);
+        // windows of only N have neither
+        self.gc_content
+            .push(window.gc_content().unwrap_or(f64::NAN));
+        self.repeat_density
+            .push(window.repeat_density().unwrap_or(f64::NAN));
+        self.id.push(window.id);
+    }
+
+    /// A dict of NumPy arrays, or of lists. The ids are always a list.
+    fn into_dict(self, py: Python<'_>, numpy: bool) -> PyResult<Bound<'_, PyDict>> {
+        if numpy {
+            // raises if NumPy isn't installed, rather than panicking
+            py.import("numpy")?;
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id)?;
+        let columns = [
+            ("start", self.start),
+            ("end", self.end),
+            ("forward", self.forward),
+            ("reverse", self.reverse),
+            ("masked_forward", self.masked_forward),
+            ("masked_reverse", self.masked_reverse),
+            ("n_count", self.n_count),
+        ];
+        for (name, column) in columns {
+            if name.starts_with("masked") && column.is_empty() {
+                continue;
+            }
+            if numpy {
+                dict.set_item(name, column.into_pyarray(py))?;
+            } else {
+                dict.set_item(name, column)?;
+            }
+        }
+        for (name, column) in [
+            ("gc_content", self.gc_content),
+            ("repeat_density", self.repeat_density),
+        ] {
+            if numpy {
+                dict.set_item(name, column.into_pyarray(py))?;
+            } else {
+                dict.set_item(name, column)?;
+            }
+        }
+        Ok(dict)
+    }
+}
+
+/// Count a telomeric repeat in windows along each sequence of a FASTA
+/// or FASTQ file, as `tidk search` does, without writing any files.
+/// Returns a dict of columns, as NumPy arrays unless `numpy=False`.
+#[pyfunction]
+#[pyo3(signature = (fasta, telomeric_repeat, window=10000, step=None, soft_mask="include", min_quality=None, numpy=true))]
+fn search(
+    fasta: PathBuf,
+    telomeric_repeat: &str,
+    window: usize,
+    step: Option<usize>,
+    soft_mask: &str,
+    min_quality: Option<u8>,
+    numpy: bool,
+) -> PyResult<Py<PyDict>> {
+    check_motif(telomeric_repeat)?;
+    let config = SearchConfig::new(fasta, telomeric_repeat, "", "")
+        .window(window)
+        .step(step)
+        .soft_mask(SoftMask::from_name(soft_mask)?)
+        .min_quality(min_quality);
+    Python::attach(|py| {
+        let columns = py.detach(|| -> crate::Result<WindowColumns> {
+            let mut columns = WindowColumns::default();
+            for window in config.window_counts()? {
+                columns.push(window?);
+            }
+            Ok(columns)
+        })?;
+        Ok(columns.into_dict(py, numpy)?.unbind())
+    })
+}
+
+/// Find candidate telomeric repeats of the lengths from the least to
+/// the greatest of `lengths`, as `tidk explore` does. Returns a list of
+/// dicts, most frequent first.
+#[pyfunction]
+#[pyo3(signature = (fasta, lengths=(5, 12), threshold=100, distance=0.01, reads=false, read_end=2000, min_quality=None))]
+fn explore(
+    fasta: PathBuf,
+    lengths: (usize, usize),
+    threshold: usize,
+    distance: f64,
+    reads: bool,
+    read_end: usize,
+    min_quality: Option<u8>,
+) -> PyResult<Vec<Py<PyDict>>> {
+    let config = ExploreConfig::new(fasta)
+        .lengths(lengths.0, lengths.1)
+        .threshold(threshold)
+        .distance(distance)
+        .reads(reads)
+        .read_end(read_end)
+        .min_quality(min_quality);
+    Python::attach(|py| {
+        let exploration = py.detach(|| crate::explore::explore(&config))?;
+        let mut candidates = Vec::new();
+        match exploration {
+            Exploration::Genome { estimates, .. } => {
+                for estimate in estimates {
+                    let candidate = PyDict::new(py);
+                    candidate.set_item("unit", estimate.unit)?;
+                    candidate.set_item("runs", estimate.runs)?;
+                    candidates.push(candidate.unbind());
+                }
+            }
+            Exploration::Reads(counts) => {
+                for (unit, count) in counts.estimates() {
+                    let candidate = PyDict::new(py);
+                    candidate.set_item("unit", unit)?;
+                    candidate.set_item("read_ends", count.read_ends)?;
+                    candidate.set_item("copies", count.copies)?;
+                    candidate.set_item("interior_copies", count.interior_copies)?;
+                    candidate.set_item("enrichment", counts.enrichment(&count))?;
+                    candidates.push(candidate.unbind());
+                }
+            }
+        }
+        Ok(candidates)
+    })
+}
+
+/// The canonical form of a repeat unit: the least rotation of it, or of
+/// its reverse complement.
+#[pyfunction]
+fn lex_min(telomeric_repeat: &str) -> PyResult<String> {
+    check_motif(telomeric_repeat)?;
+    Ok(utils::lex_min(&telomeric_repeat.to_uppercase()))
+}
+
+/// The reverse complement of a repeat unit.
+#[pyfunction]
+fn reverse_complement(telomeric_repeat: &str) -> PyResult<String> {
+    check_motif(telomeric_repeat)?;
+    Ok(utils::reverse_complement(&telomeric_repeat.to_uppercase()))
+}
+
+/// The clades in the database, fetched by `tidk build`.
+#[pyfunction]
+fn clades() -> PyResult<Vec<String>> {
+    Ok(crate::clades::get_clades()?)
+}
+
+/// The telomeric repeats of a clade in the database.
+#[pyfunction]
+fn clade_repeats(clade: &str) -> PyResult<Vec<String>> {
+    let telomere = crate::clades::return_telomere_sequence(clade.to_string())?;
+    Ok(telomere.seq.0)
+}
+
+/// The `tidk` Python module, built with maturin.
+#[pymodule]
+fn tidk(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(search, m)?)?;
+    m.add_function(wrap_pyfunction!(explore, m)?)?;
+    m.add_function(wrap_pyfunction!(lex_min, m)?)?;
+    m.add_function(wrap_pyfunction!(reverse_complement, m)?)?;
+    m.add_function(wrap_pyfunction!(clades, m)?)?;
+    m.add_function(wrap_pyfunction!(clade_repeats, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::count_windows;
+
+    #[test]
+    fn test_window_columns() {
+        let mut columns = WindowColumns::default();
+        let windows = count_windows(
+            "chr1",
+            b"TTAGGTTAGGNNNNNNNNNN",
+            "TTAGG",
+            10,
+            10,
+            SoftMask::Include,
+        )
+        .unwrap();
+        for window in windows {
+            columns.push(window);
+        }
+        assert_eq!(columns.id, vec!["chr1", "chr1"]);
+        assert_eq!((columns.start, columns.end), (vec![0, 10], vec![10, 20]));
+        assert_eq!(columns.forward, vec![2, 0]);
+        assert!(columns.masked_forward.is_empty());
+        assert_eq!(columns.gc_content[0], 0.4);
+        assert!(columns.gc_content[1].is_nan());
+    }
+}
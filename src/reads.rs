@@ -0,0 +1,587 @@
+use crate::{
+    ends::{ContigEnd, EndIndex, MatchOptions},
+    input::{self, Format, Sequence},
+    utils, Error, Result, SubCommand,
+};
+use bio::io::{fasta, fastq};
+use std::fs::{create_dir_all, File};
+use std::io::{LineWriter, Write};
+use std::path::PathBuf;
+use std::str;
+
+/// The options of `tidk reads`. Build with [`ReadsConfig::new()`], then
+/// set any options which aren't the defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadsConfig {
+    /// The input reads, FASTA or FASTQ, optionally gzipped.
+    pub reads: PathBuf,
+    /// The telomeric repeat to search for.
+    pub telomeric_repeat: String,
+    /// The output file name, without extension.
+    pub output: String,
+    /// The directory the output is written to.
+    pub dir: PathBuf,
+    pub array_options: ArrayOptions,
+    /// The bin width of the length histogram.
+    pub bin_width: usize,
+    /// Also write the telomeric reads.
+    pub extract: bool,
+    /// An assembly, whose contig ends the telomeric reads are matched to.
+    pub assembly: Option<PathBuf>,
+    pub match_options: MatchOptions,
+    /// Write a log file.
+    pub log: bool,
+}
+
+impl ReadsConfig {
+    /// The default options, searching `reads` for `telomeric_repeat` and
+    /// writing to `dir`/`output`.
+    pub fn new(
+        reads: impl Into<PathBuf>,
+        telomeric_repeat: &str,
+        output: &str,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            reads: reads.into(),
+            telomeric_repeat: telomeric_repeat.to_string(),
+            output: output.to_string(),
+            dir: dir.into(),
+            array_options: ArrayOptions {
+                max_distance: 100,
+                max_gap: 50,
+                min_length: 100,
+            },
+            bin_width: 1000,
+            extract: false,
+            assembly: None,
+            match_options: MatchOptions {
+                flank: 5000,
+                window: 20000,
+                k: 21,
+                min_shared: 20,
+            },
+            log: false,
+        }
+    }
+
+    pub fn array_options(mut self, array_options: ArrayOptions) -> Self {
+        self.array_options = array_options;
+        self
+    }
+
+    pub fn bin_width(mut self, bin_width: usize) -> Self {
+        self.bin_width = bin_width;
+        self
+    }
+
+    pub fn extract(mut self, extract: bool) -> Self {
+        self.extract = extract;
+        self
+    }
+
+    pub fn assembly(mut self, assembly: Option<PathBuf>) -> Self {
+        self.assembly = assembly;
+        self
+    }
+
+    pub fn match_options(mut self, match_options: MatchOptions) -> Self {
+        self.match_options = match_options;
+        self
+    }
+
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+}
+
+/// What `tidk reads` found, and the files written.
+#[derive(Debug, Clone)]
+pub struct ReadsSummary {
+    pub reads: usize,
+    /// The lengths of the terminal arrays.
+    pub lengths: LengthSummary,
+    /// With an assembly, each contig end and its supporting reads.
+    pub contig_ends: Option<Vec<ContigEnd>>,
+    pub files: Vec<PathBuf>,
+}
+
+/// The entry point for `tidk reads`.
+///
+/// Finds reads which start or end in a telomeric array, and estimates
+/// telomere length from the length of these arrays.
+pub fn reads(config: &ReadsConfig) -> Result<ReadsSummary> {
+    let telomeric_repeat = &config.telomeric_repeat;
+    let outdir = &config.dir;
+    let output = &config.output;
+    let options = config.array_options;
+    let bin_width = config.bin_width;
+    if bin_width == 0 {
+        return Err(Error::InvalidOption(
+            "The histogram bin width must be greater than zero.".to_string(),
+        ));
+    }
+    crate::error::check_motif(telomeric_repeat)?;
+
+    // optionally, the contig ends to match telomeric reads to
+    let mut end_index = match &config.assembly {
+        Some(assembly) => {
+            eprintln!("[+]\tIndexing contig ends of: {}", assembly.display());
+            Some(EndIndex::build(
+                assembly,
+                telomeric_repeat,
+                &options,
+                config.match_options,
+            )?)
+        }
+        None => None,
+    };
+
+    let (format, sequences) = input::read_sequences(&config.reads, None)?;
+    eprintln!(
+        "[+]\tSearching {} reads for terminal telomeric arrays of: {}",
+        format, telomeric_repeat
+    );
+
+    create_dir_all(outdir)?;
+    let mut files = Vec::new();
+    let file_name = outdir.join(format!("{}_telomeric_reads.tsv", output));
+    let mut reads_file = LineWriter::new(File::create(&file_name)?);
+    writeln!(
+        reads_file,
+        "read_id\tread_length\tread_end\tstrand\tarray_length\tdistance_from_end"
+    )?;
+
+    // optionally, the telomeric reads themselves
+    let mut extracted = if config.extract {
+        Some(ReadWriter::create(
+            &format!("{}/{}_telomeric_reads", outdir.display(), output),
+            format,
+        )?)
+    } else {
+        None
+    };
+
+    let mut read_number = 0;
+    let mut array_lengths = Vec::new();
+    for sequence in sequences {
+        let sequence = sequence?;
+        read_number += 1;
+        let arrays = terminal_arrays(&sequence.seq, telomeric_repeat, &options)?;
+        if let Some(writer) = extracted.as_mut().filter(|_| !arrays.is_empty()) {
+            writer.write(&sequence)?;
+        }
+        for array in arrays {
+            writeln!(
+                reads_file,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                sequence.id,
+                sequence.seq.len(),
+                array.read_end,
+                array.strand,
+                array.length(),
+                array.distance_from_end(sequence.seq.len())
+            )?;
+            if let Some(index) = end_index.as_mut() {
+                index.add_read(&sequence.seq, &array);
+            }
+            array_lengths.push(array.length());
+        }
+    }
+    if read_number == 0 {
+        return Err(Error::EmptyInput(config.reads.clone()));
+    }
+    eprintln!(
+        "[+]\t{} telomeric read ends found in {} reads",
+        array_lengths.len(),
+        read_number
+    );
+    eprintln!("[+]\tPer read arrays written to: {}", file_name.display());
+    files.push(file_name);
+    if let Some(writer) = extracted {
+        let file_name = writer.finish()?;
+        eprintln!("[+]\tTelomeric reads written to: {}", file_name);
+        files.push(file_name.into());
+    }
+
+    let contig_ends = match end_index {
+        Some(index) => {
+            let ends_name = outdir.join(format!("{}_contig_ends.tsv", output));
+            let mut ends_file = LineWriter::new(File::create(&ends_name)?);
+            index.write_tsv(&mut ends_file)?;
+            eprintln!(
+                "[+]\t{} of {} contig ends supported by telomeric reads, written to: {}",
+                index
+                    .ends
+                    .iter()
+                    .filter(|end| !end.read_arrays.is_empty())
+                    .count(),
+                index.ends.len(),
+                ends_name.display()
+            );
+            files.push(ends_name);
+            Some(index.ends)
+        }
+        None => None,
+    };
+
+    let histogram_name = outdir.join(format!("{}_telomere_length_histogram.tsv", output));
+    let mut histogram_file = LineWriter::new(File::create(&histogram_name)?);
+    writeln!(histogram_file, "bin_start\tbin_end\tcount")?;
+    for (start, count) in histogram(&array_lengths, bin_width) {
+        writeln!(
+            histogram_file,
+            "{}\t{}\t{}",
+            start,
+            start + bin_width,
+            count
+        )?;
+    }
+    eprintln!("[+]\tHistogram written to: {}", histogram_name.display());
+    files.push(histogram_name);
+
+    // optional log file
+    if config.log {
+        SubCommand::Reads(config).log()?;
+    }
+
+    Ok(ReadsSummary {
+        reads: read_number,
+        lengths: LengthSummary::new(&mut array_lengths),
+        contig_ends,
+        files,
+    })
+}
+
+/// Writes reads in the format they were read in.
+enum ReadWriter {
+    Fasta(fasta::Writer<File>, String),
+    Fastq(fastq::Writer<File>, String),
+}
+
+impl ReadWriter {
+    /// Create `{prefix}.fa` or `{prefix}.fq`.
+    fn create(prefix: &str, format: Format) -> Result<Self> {
+        Ok(match format {
+            Format::Fasta => {
+                let file_name = format!("{}.fa", prefix);
+                Self::Fasta(fasta::Writer::to_file(&file_name)?, file_name)
+            }
+            Format::Fastq => {
+                let file_name = format!("{}.fq", prefix);
+                Self::Fastq(fastq::Writer::to_file(&file_name)?, file_name)
+            }
+        })
+    }
+
+    fn write(&mut self, sequence: &Sequence) -> Result<()> {
+        match self {
+            Self::Fasta(writer, _) => writer.write(&sequence.id, None, &sequence.seq)?,
+            Self::Fastq(writer, _) => writer.write(
+                &sequence.id,
+                None,
+                &sequence.seq,
+                sequence.qual.as_deref().unwrap_or_default(),
+            )?,
+        }
+        Ok(())
+    }
+
+    /// Flush the file, returning its name.
+    fn finish(self) -> Result<String> {
+        match self {
+            Self::Fasta(mut writer, file_name) => {
+                writer.flush()?;
+                Ok(file_name)
+            }
+            Self::Fastq(mut writer, file_name) => {
+                writer.flush()?;
+                Ok(file_name)
+            }
+        }
+    }
+}
+
+/// What counts as a terminal telomeric array on a read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayOptions {
+    /// The greatest distance from the read end to the array, to allow
+    /// for adapters and low quality read ends.
+    pub max_distance: usize,
+    /// The greatest gap between adjacent repeats in an array, to allow
+    /// for sequencing errors.
+    pub max_gap: usize,
+    /// The shortest array reported.
+    pub min_length: usize,
+}
+
+/// A telomeric array at one end of a read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalArray {
+    /// "start" or "end" of the read.
+    pub read_end: &'static str,
+    /// '+' for the repeat, '-' for its reverse complement.
+    pub strand: char,
+    /// 0-based start on the read.
+    pub start: usize,
+    /// Exclusive end on the read.
+    pub end: usize,
+}
+
+impl TerminalArray {
+    pub fn length(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// The bases between the array and the end of the read it lies at.
+    pub fn distance_from_end(&self, read_length: usize) -> usize {
+        if self.read_end == "start" {
+            self.start
+        } else {
+            read_length - self.end
+        }
+    }
+}
+
+/// Find the telomeric arrays at the start and end of a read. Each end
+/// gets the longer of the arrays of the repeat and its reverse
+/// complement, if either is at least the minimum length.
+pub fn terminal_arrays(
+    read: &[u8],
+    telomeric_repeat: &str,
+    options: &ArrayOptions,
+) -> Result<Vec<TerminalArray>> {
+    let read = str::from_utf8(read)?.to_uppercase();
+    let forward_telomeric_seq = telomeric_repeat.to_uppercase();
+    let reverse_telomeric_seq = utils::reverse_complement(&forward_telomeric_seq);
+    let length = forward_telomeric_seq.len();
+
+    let mut strands = vec![(
+        '+',
+        utils::find_motifs(&forward_telomeric_seq, &read).indexes,
+    )];
+    if reverse_telomeric_seq != forward_telomeric_seq {
+        strands.push((
+            '-',
+            utils::find_motifs(&reverse_telomeric_seq, &read).indexes,
+        ));
+    }
+
+    let mut arrays = Vec::new();
+    for read_end in ["start", "end"] {
+        let longest = strands
+            .iter()
+            .filter_map(|(strand, indexes)| {
+                let (start, end) = if read_end == "start" {
+                    array_at_start(indexes, length, options)?
+                } else {
+                    array_at_end(indexes, length, read.len(), options)?
+                };
+                Some(TerminalArray {
+                    read_end,
+                    strand: *strand,
+                    start,
+                    end,
+                })
+            })
+            .filter(|array| array.length() >= options.min_length)
+            .max_by_key(|array| array.length());
+        arrays.extend(longest);
+    }
+    // a read which is all array has it at both ends
+    if let [first, second] = arrays[..] {
+        if first.start == second.start && first.end == second.end {
+            arrays.pop();
+        }
+    }
+    Ok(arrays)
+}
+
+/// The array chained from the first repeat, if it's close enough to
+/// the start of the read.
+fn array_at_start(
+    indexes: &[usize],
+    length: usize,
+    options: &ArrayOptions,
+) -> Option<(usize, usize)> {
+    let (&start, rest) = indexes.split_first()?;
+    if start > options.max_distance {
+        return None;
+    }
+    let mut end = start + length;
+    for &index in rest {
+        if index > end + options.max_gap {
+            break;
+        }
+        end = end.max(index + length);
+    }
+    Some((start, end))
+}
+
+/// The array chained back from the last repeat, if it's close enough to
+/// the end of the read.
+fn array_at_end(
+    indexes: &[usize],
+    length: usize,
+    read_length: usize,
+    options: &ArrayOptions,
+) -> Option<(usize, usize)> {
+    let (&last, rest) = indexes.split_last()?;
+    let end = last + length;
+    if read_length - end > options.max_distance {
+        return None;
+    }
+    let mut start = last;
+    for &index in rest.iter().rev() {
+        if index + length + options.max_gap < start {
+            break;
+        }
+        start = start.min(index);
+    }
+    Some((start, end))
+}
+
+/// The distribution of telomeric array lengths.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthSummary {
+    pub count: usize,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    /// The length at which arrays this long or longer hold half of the
+    /// bases of all arrays.
+    pub n50: Option<usize>,
+}
+
+impl LengthSummary {
+    /// Summarise the lengths, sorting them in place.
+    pub fn new(lengths: &mut [usize]) -> Self {
+        lengths.sort_unstable();
+        let count = lengths.len();
+        if count == 0 {
+            return Self {
+                count,
+                mean: None,
+                median: None,
+                n50: None,
+            };
+        }
+        let total: usize = lengths.iter().sum();
+        let median = if count.is_multiple_of(2) {
+            (lengths[count / 2 - 1] + lengths[count / 2]) as f64 / 2.0
+        } else {
+            lengths[count / 2] as f64
+        };
+        let mut cumulative = 0;
+        let n50 = lengths.iter().rev().find(|&&length| {
+            cumulative += length;
+            cumulative * 2 >= total
+        });
+        Self {
+            count,
+            mean: Some(total as f64 / count as f64),
+            median: Some(median),
+            n50: n50.copied(),
+        }
+    }
+}
+
+/// The count of lengths in each bin of `bin_width`, from zero up to the
+/// bin of the longest.
+pub fn histogram(lengths: &[usize], bin_width: usize) -> Vec<(usize, usize)> {
+    let Some(max) = lengths.iter().max() else {
+        return Vec::new();
+    };
+    let mut counts = vec![0; max / bin_width + 1];
+    for length in lengths {
+        counts[length / bin_width] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| (bin * bin_width, count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: ArrayOptions = ArrayOptions {
+        max_distance: 5,
+        max_gap: 3,
+        min_length: 10,
+    };
+
+    #[test]
+    fn test_terminal_arrays() {
+        // an adapter, then an array with a sequencing error, at the start;
+        // nothing at the end
+        let read = b"ACGCCTAACCTAAGCCTAACCTAAGATTTACGATCGATCGATCGTTAGG";
+        let arrays = terminal_arrays(read, "TTAGG", &OPTIONS).unwrap();
+        assert_eq!(
+            arrays,
+            vec![TerminalArray {
+                read_end: "start",
+                strand: '-',
+                start: 3,
+                end: 24
+            }]
+        );
+        assert_eq!(arrays[0].distance_from_end(read.len()), 3);
+
+        // and at the end, in lower case
+        let read = b"GATTTACGATCGATCGATCttaggttaggttaggA";
+        let arrays = terminal_arrays(read, "TTAGG", &OPTIONS).unwrap();
+        assert_eq!(arrays.len(), 1);
+        assert_eq!((arrays[0].read_end, arrays[0].strand), ("end", '+'));
+        assert_eq!(arrays[0].length(), 15);
+        assert_eq!(arrays[0].distance_from_end(read.len()), 1);
+
+        // too short
+        assert!(terminal_arrays(b"TTAGGATCGATCGATCG", "TTAGG", &OPTIONS)
+            .unwrap()
+            .is_empty());
+        // all array is one array
+        assert_eq!(
+            terminal_arrays(b"TTAGGTTAGGTTAGG", "TTAGG", &OPTIONS)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_read_writer() {
+        let read = Sequence {
+            id: "read1".to_string(),
+            seq: b"TTAGGTTAGG".to_vec(),
+            qual: Some(b"IIIIIIIII#".to_vec()),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let prefix = dir.path().join("extracted");
+        let mut writer = ReadWriter::create(prefix.to_str().unwrap(), Format::Fastq).unwrap();
+        writer.write(&read).unwrap();
+        let file_name = writer.finish().unwrap();
+        assert!(file_name.ends_with(".fq"));
+
+        let (format, sequences) =
+            input::read_sequences(std::path::Path::new(&file_name), None).unwrap();
+        assert_eq!(format, Format::Fastq);
+        let sequences: Vec<Sequence> = sequences.map(|s| s.unwrap()).collect();
+        assert_eq!(sequences, vec![read]);
+    }
+
+    #[test]
+    fn test_length_summary() {
+        let summary = LengthSummary::new(&mut [2, 8, 3, 5]);
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.mean, Some(4.5));
+        assert_eq!(summary.median, Some(4.0));
+        // 8 alone is 8 of 18 bases, 8 + 5 is over half
+        assert_eq!(summary.n50, Some(5));
+        assert_eq!(LengthSummary::new(&mut []).mean, None);
+
+        assert_eq!(histogram(&[2, 8, 3, 5], 3), vec![(0, 1), (3, 2), (6, 1)]);
+    }
+}
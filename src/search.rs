@@ -1,157 +1,523 @@
-use crate::{utils, SubCommand};
-use anyhow::Result;
-use bio::io::fasta;
+use crate::{
+    bigwig::CountTracks,
+    input::{self, Sequence},
+    utils,
+    windows::{self, Extension, SequenceSummary, SoftMask, WindowCounts, WindowsSummary},
+    Error, Result, SubCommand,
+};
 use std::fs::{create_dir_all, File};
 use std::io::LineWriter;
 use std::io::Write;
 use std::path::PathBuf;
 use std::str;
 
+/// The options of `tidk search`. Build with [`SearchConfig::new()`],
+/// then set any options which aren't the defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// The input FASTA or FASTQ, optionally gzipped.
+    pub fasta: PathBuf,
+    /// The telomeric repeat to search for.
+    pub telomeric_repeat: String,
+    /// The output file name, without extension.
+    pub output: String,
+    /// The directory the output is written to.
+    pub dir: PathBuf,
+    /// For FASTQ, mask bases below this Phred quality with N.
+    pub min_quality: Option<u8>,
+    pub window: usize,
+    /// Tiles the windows if `None`.
+    pub step: Option<usize>,
+    pub extension: Extension,
+    pub soft_mask: SoftMask,
+    /// Write densities, not counts, to bedGraph and bigWig.
+    pub normalise: bool,
+    /// Separate forward, reverse and total bigWig tracks.
+    pub stranded: bool,
+    /// Also write every occurrence of the repeat.
+    pub hits: Option<HitsFormat>,
+    /// Merge tandem occurrences into runs in the hits.
+    pub merge_hits: bool,
+    /// Write a log file.
+    pub log: bool,
+}
+
+impl SearchConfig {
+    /// The default options, searching `fasta` for `telomeric_repeat` and
+    /// writing to `dir`/`output`.
+    pub fn new(
+        fasta: impl Into<PathBuf>,
+        telomeric_repeat: &str,
+        output: &str,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            fasta: fasta.into(),
+            telomeric_repeat: telomeric_repeat.to_string(),
+            output: output.to_string(),
+            dir: dir.into(),
+            min_quality: None,
+            window: 10000,
+            step: None,
+            extension: Extension::Tsv,
+            soft_mask: SoftMask::Include,
+            normalise: false,
+            stranded: false,
+            hits: None,
+            merge_hits: false,
+            log: false,
+        }
+    }
+
+    pub fn min_quality(mut self, min_quality: Option<u8>) -> Self {
+        self.min_quality = min_quality;
+        self
+    }
+
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn step(mut self, step: Option<usize>) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    pub fn soft_mask(mut self, soft_mask: SoftMask) -> Self {
+        self.soft_mask = soft_mask;
+        self
+    }
+
+    pub fn normalise(mut self, normalise: bool) -> Self {
+        self.normalise = normalise;
+        self
+    }
+
+    pub fn stranded(mut self, stranded: bool) -> Self {
+        self.stranded = stranded;
+        self
+    }
+
+    pub fn hits(mut self, hits: Option<HitsFormat>) -> Self {
+        self.hits = hits;
+        self
+    }
+
+    pub fn merge_hits(mut self, merge_hits: bool) -> Self {
+        self.merge_hits = merge_hits;
+        self
+    }
+
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Count the repeat in the windows of each sequence, as they're read,
+    /// rather than writing them out.
+    pub fn window_counts(&self) -> Result<WindowCounts> {
+        let (_, sequences) = input::read_sequences(&self.fasta, self.min_quality)?;
+        Ok(WindowCounts::new(
+            sequences,
+            vec![self.telomeric_repeat.clone()],
+            self.window,
+            self.step.unwrap_or(self.window),
+            self.soft_mask,
+        ))
+    }
+
+    /// The file the windows are written to (or the prefix of the
+    /// bigWig tracks).
+    pub fn windows_file(&self) -> PathBuf {
+        self.dir.join(format!(
+            "{}_telomeric_repeat_windows.{}",
+            self.output,
+            self.extension.name()
+        ))
+    }
+
+    /// The file the hits are written to with `--hits`.
+    pub fn hits_file(&self, format: HitsFormat) -> PathBuf {
+        self.dir.join(format!(
+            "{}_telomeric_repeat_hits.{}",
+            self.output,
+            format.name()
+        ))
+    }
+}
+
+/// The format of the repeat occurrences written with `--hits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitsFormat {
+    Bed,
+    Gff3,
+}
+
+impl HitsFormat {
+    /// From the value of `--hits`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "bed" => Ok(HitsFormat::Bed),
+            "gff3" => Ok(HitsFormat::Gff3),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown hits format {}, expected bed or gff3.",
+                name
+            ))),
+        }
+    }
+
+    /// The file extension.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HitsFormat::Bed => "bed",
+            HitsFormat::Gff3 => "gff3",
+        }
+    }
+}
+
 /// The entry point for `tidk search`.
-pub fn search(matches: &clap::ArgMatches, sc: SubCommand) -> Result<()> {
-    let input_fasta = matches
-        .get_one::<PathBuf>("fasta")
-        .expect("errored by clap");
-    let reader = fasta::Reader::from_file(input_fasta)?;
-
-    let telomeric_repeat = matches
-        .get_one::<String>("string")
-        .expect("errored by clap");
-    let extension = matches
-        .get_one::<String>("extension")
-        .expect("defaulted by clap");
+pub fn search(config: &SearchConfig) -> Result<WindowsSummary> {
+    crate::error::check_motif(&config.telomeric_repeat)?;
+    let (_, sequences) = input::read_sequences(&config.fasta, config.min_quality)?;
+
+    let telomeric_repeat = &config.telomeric_repeat;
     eprintln!(
         "[+]\tSearching genome for telomeric repeat: {}",
         telomeric_repeat
     );
 
-    let window_size = *matches
-        .get_one::<usize>("window")
-        .expect("defaulted by clap");
-    let outdir = matches
-        .get_one::<PathBuf>("dir")
-        .expect("defaulted by clap");
-    let output = matches
-        .get_one::<String>("output")
-        .expect("errored by clap");
+    // tiles, unless a step is given
+    let step = config.step.unwrap_or(config.window);
+    let outdir = &config.dir;
+    let output = &config.output;
 
     // create directory for output
     create_dir_all(outdir)?;
+    let mut summary = WindowsSummary::default();
 
-    // create file
-    let file_name = format!(
-        "{}/{}{}{}",
-        outdir.display(),
-        output,
-        "_telomeric_repeat_windows.",
-        extension
-    );
-    let search_file = File::create(file_name)?;
+    // create file, or bigWig tracks
+    let (search_file, mut bigwig): (Box<dyn Write>, _) = if config.extension == Extension::BigWig {
+        let prefix = format!("{}/{}_telomeric_repeat_windows", outdir.display(), output);
+        summary
+            .files
+            .extend(CountTracks::file_names(&prefix, config.stranded));
+        (
+            Box::new(std::io::sink()),
+            Some(CountTracks::create(
+                &prefix,
+                config.stranded,
+                config.window,
+                step,
+                config.normalise,
+            )?),
+        )
+    } else {
+        let file_name = config.windows_file();
+        let file = File::create(&file_name)?;
+        summary.files.push(file_name);
+        (Box::new(file), None)
+    };
     let mut search_file = LineWriter::new(search_file);
 
+    // optionally, every repeat occurrence too
+    let mut hits_file = match config.hits {
+        Some(format) => {
+            let file_name = config.hits_file(format);
+            let mut file = LineWriter::new(File::create(&file_name)?);
+            if format == HitsFormat::Gff3 {
+                writeln!(file, "##gff-version 3")?;
+            }
+            summary.files.push(file_name);
+            Some((file, format))
+        }
+        None => None,
+    };
+
     // add headers if extension/file type is a csv
-    if extension == "tsv" {
-        writeln!(
-            search_file,
-            "id\twindow\tforward_repeat_number\treverse_repeat_number\ttelomeric_repeat"
-        )?;
+    if config.extension == Extension::Tsv {
+        windows::write_tsv_header(&mut search_file)?;
     }
 
-    // iterate over the fasta records
-    for result in reader.records() {
+    // iterate over the fasta or fastq records
+    for result in sequences {
         let record = result?;
-        let id = record.id().to_owned();
+        let id = record.id.clone();
+        let length = record.seq.len();
+
+        if let Some((file, format)) = hits_file.as_mut() {
+            let hits = find_hits(
+                &record.seq,
+                telomeric_repeat,
+                config.merge_hits,
+                config.soft_mask == SoftMask::Skip,
+            )?;
+            write_hits(
+                file,
+                &id,
+                record.seq.len(),
+                &hits,
+                telomeric_repeat,
+                *format,
+            )?;
+        }
+
+        let output = match (bigwig.as_mut(), config.extension) {
+            (Some(bigwig), _) => Output::BigWig(bigwig),
+            (None, Extension::BedGraph) => Output::BedGraph {
+                normalise: config.normalise,
+            },
+            _ => Output::Tsv,
+        };
 
         // fn window counter
-        write_window_counts(
+        let windows = write_window_counts(
             record,
             &mut search_file,
             telomeric_repeat,
-            window_size,
-            id.clone(),
-            extension,
+            config.window,
+            step,
+            config.soft_mask,
+            output,
         )?;
 
         eprintln!("[+]\tChromosome {} processed", id);
+        summary.sequences.push(SequenceSummary {
+            id,
+            length,
+            windows,
+        });
+    }
+    if summary.sequences.is_empty() {
+        return Err(Error::EmptyInput(config.fasta.clone()));
+    }
+    if let Some(bigwig) = bigwig {
+        bigwig.finish()?;
     }
     eprintln!("[+]\tFinished searching genome.");
 
     // optional log file
-    sc.log(matches)?;
+    if config.log {
+        SubCommand::Search(config).log()?;
+    }
 
-    Ok(())
+    Ok(summary)
+}
+
+/// Where the window counts are written.
+enum Output<'a> {
+    /// The TSV, with counts and densities.
+    Tsv,
+    /// The total count, or density, of each window.
+    BedGraph {
+        normalise: bool,
+    },
+    BigWig(&'a mut CountTracks),
 }
 
 /// Iterate over windows, counting occurrences of specified string
-/// and write to file on the fly.
+/// and write to file on the fly. Returns the number of windows.
 fn write_window_counts<T: std::io::Write>(
-    sequence: bio::io::fasta::Record,
+    sequence: Sequence,
     file: &mut LineWriter<T>,
     telomeric_repeat: &str,
     window_size: usize,
-    id: String,
-    extension: &str,
-) -> Result<()> {
-    // get forward and reverse sequences, and length
-    // to remove overlapping matches.
-    let forward_telomeric_seq = telomeric_repeat.to_uppercase();
-    let reverse_telomeric_seq = utils::reverse_complement(&forward_telomeric_seq).to_uppercase();
-    let telomeric_length = forward_telomeric_seq.len();
+    step: usize,
+    soft_mask: SoftMask,
+    mut output: Output,
+) -> Result<usize> {
+    let id = &sequence.id;
+    if let Output::BigWig(bigwig) = &mut output {
+        bigwig.add_chromosome(id, sequence.seq.len())?;
+    }
 
-    // create the iterator in each loop iteration isnt costly is it?
-    let windows = sequence.seq().chunks(window_size);
-    // keep track of the window size
+    // iterate over windows
+    let windows = windows::count_windows(
+        id,
+        &sequence.seq,
+        telomeric_repeat,
+        window_size,
+        step,
+        soft_mask,
+    )?;
+    for window in &windows {
+        // bedGraph intervals can't overlap, as overlapping windows do
+        let (start, end) = window.track_interval(window_size, step);
+        match &mut output {
+            Output::BigWig(bigwig) => bigwig.add(window)?,
+            Output::Tsv => {
+                windows::write_tsv_row(file, window)?;
+            }
+            Output::BedGraph { .. } if start == end => {}
+            Output::BedGraph { normalise: false } => {
+                // for bedgraph only four columns, and sum the forward & reverse for convenience
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\t{}",
+                    id,
+                    start,
+                    end,
+                    window.forward + window.reverse,
+                )?;
+            }
+            Output::BedGraph { normalise: true } => {
+                // all N windows have no density, so are left out
+                if let Some(density) = window.repeat_density() {
+                    writeln!(file, "{}\t{}\t{}\t{:.4}", id, start, end, density)?;
+                }
+            }
+        }
+    }
 
-    let mut start = 0;
-    let mut end = window_size;
+    Ok(windows.len())
+}
 
-    // iterate over windows
-    for (i, window) in windows.enumerate() {
-        // make window uppercase
-        let windows_upper = str::from_utf8(window)?.to_uppercase();
-        // for each window, find the motifs in this
-        let forward_motif = utils::find_motifs(&forward_telomeric_seq, &windows_upper);
-        let reverse_motif = utils::find_motifs(&reverse_telomeric_seq, &windows_upper);
-
-        // remove overlapping matches
-        // not sure this is necessary, but thought it might be...
-        let forward_motif_noverlap =
-            utils::remove_overlapping_indexes(forward_motif, telomeric_length);
-        let reverse_motif_noverlap =
-            utils::remove_overlapping_indexes(reverse_motif, telomeric_length);
-
-        // the number of matches for forward/reverse
-        let forward_repeat_number = forward_motif_noverlap.len();
-        let reverse_repeat_number = reverse_motif_noverlap.len();
-        // write to file
-        // increment window
-        if i != 0 {
-            start += window_size;
-            end += window_size;
+/// A single occurrence of the telomeric repeat, or a run of
+/// adjacent occurrences on the same strand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    /// 0-based start.
+    pub start: usize,
+    /// Exclusive end.
+    pub end: usize,
+    /// '+' for the repeat, '-' for its reverse complement, or '.' if
+    /// the repeat is its own reverse complement.
+    pub strand: char,
+    /// The number of repeat units.
+    pub copies: usize,
+}
+
+/// Find every non-overlapping occurrence of the telomeric repeat on
+/// both strands of a sequence, sorted by position. If `merge`, tandem
+/// occurrences on the same strand are merged into runs. If
+/// `skip_masked`, occurrences in soft-masked sequence are left out.
+pub fn find_hits(
+    sequence: &[u8],
+    telomeric_repeat: &str,
+    merge: bool,
+    skip_masked: bool,
+) -> Result<Vec<Hit>> {
+    let masked = sequence;
+    let sequence = str::from_utf8(sequence)?.to_uppercase();
+    let forward_telomeric_seq = telomeric_repeat.to_uppercase();
+    let reverse_telomeric_seq = utils::reverse_complement(&forward_telomeric_seq);
+    let length = forward_telomeric_seq.len();
+
+    let palindrome = forward_telomeric_seq == reverse_telomeric_seq;
+    let mut strands = vec![(
+        forward_telomeric_seq.as_str(),
+        if palindrome { '.' } else { '+' },
+    )];
+    if !palindrome {
+        strands.push((reverse_telomeric_seq.as_str(), '-'));
+    }
+
+    let mut hits = Vec::new();
+    for (motif, strand) in strands {
+        let indexes = utils::find_motifs(motif, &sequence).indexes;
+        let mut strand_hits: Vec<Hit> = Vec::new();
+        for start in indexes {
+            if skip_masked
+                && masked[start..start + length]
+                    .iter()
+                    .any(u8::is_ascii_lowercase)
+            {
+                continue;
+            }
+            // skip occurrences overlapping the previous one
+            if strand_hits
+                .last()
+                .is_some_and(|previous| start < previous.end)
+            {
+                continue;
+            }
+            match strand_hits.last_mut() {
+                Some(previous) if merge && start == previous.end => {
+                    previous.end = start + length;
+                    previous.copies += 1;
+                }
+                _ => strand_hits.push(Hit {
+                    start,
+                    end: start + length,
+                    strand,
+                    copies: 1,
+                }),
+            }
         }
-        if end > sequence.seq().len() {
-            end = sequence.seq().len();
+        hits.extend(strand_hits);
+    }
+    hits.sort_by_key(|hit| (hit.start, hit.end));
+    Ok(hits)
+}
+
+/// The greatest score a BED file allows.
+const BED_SCORE_MAX: usize = 1000;
+
+/// Percent-encode the characters with a meaning in a GFF3 seqid or
+/// attribute value.
+fn escape_gff3(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ';' | '=' | ',' | '%' | '&' | '\t' | '\n' | '\r' => {
+                escaped.push_str(&format!("%{:02X}", c as u32))
+            }
+            _ => escaped.push(c),
         }
+    }
+    escaped
+}
 
-        if extension == "tsv" {
+/// Write the hits on a sequence as BED or GFF3. The BED score is the
+/// number of copies, capped at 1000, whereas the GFF3 keeps the exact
+/// number in its `copies` attribute.
+fn write_hits<T: std::io::Write>(
+    file: &mut LineWriter<T>,
+    id: &str,
+    sequence_length: usize,
+    hits: &[Hit],
+    telomeric_repeat: &str,
+    format: HitsFormat,
+) -> Result<()> {
+    let telomeric_repeat = telomeric_repeat.to_uppercase();
+    let gff3_id = escape_gff3(id);
+    if format == HitsFormat::Gff3 {
+        writeln!(file, "##sequence-region {} 1 {}", gff3_id, sequence_length)?;
+    }
+    for (i, hit) in hits.iter().enumerate() {
+        if format == HitsFormat::Gff3 {
+            // GFF3 is 1-based and inclusive
             writeln!(
                 file,
-                "{}\t{}\t{}\t{}\t{}",
-                id, end, forward_repeat_number, reverse_repeat_number, forward_telomeric_seq
+                "{}\ttidk\ttelomeric_repeat\t{}\t{}\t{}\t{}\t.\tID={}_hit{};Name={};copies={}",
+                gff3_id,
+                hit.start + 1,
+                hit.end,
+                hit.copies,
+                hit.strand,
+                gff3_id,
+                i + 1,
+                escape_gff3(&telomeric_repeat),
+                hit.copies
             )?;
         } else {
-            // for bedgraph only four columns, and sum the forward & reverse for convenience
             writeln!(
                 file,
-                "{}\t{}\t{}\t{}",
+                "{}\t{}\t{}\t{}\t{}\t{}",
                 id,
-                start,
-                end,
-                forward_repeat_number + reverse_repeat_number,
+                hit.start,
+                hit.end,
+                telomeric_repeat,
+                hit.copies.min(BED_SCORE_MAX),
+                hit.strand
             )?;
         }
     }
-
     Ok(())
 }
 
@@ -159,20 +525,25 @@ fn write_window_counts<T: std::io::Write>(
 mod tests {
     use std::io::{LineWriter, Read};
 
-    use super::write_window_counts;
+    use super::{
+        escape_gff3, find_hits, search, write_hits, write_window_counts, Error, Extension, Hit,
+        HitsFormat, Output, SearchConfig, Sequence, SoftMask,
+    };
 
-    // a wrapper for making a bio::io::fasta record
-    fn make_record(id: &str, seq: &[u8]) -> bio::io::fasta::Record {
-        bio::io::fasta::Record::with_attrs(id, None, seq)
+    // a wrapper for making a sequence record
+    fn make_record(id: &str, seq: &[u8]) -> Sequence {
+        Sequence {
+            id: id.to_string(),
+            seq: seq.to_vec(),
+            qual: None,
+        }
     }
 
     // take a record, write to a vector (fake file), then read out of this the output.
-    fn calc_windows(rec: bio::io::fasta::Record, repeat: &str, ws: usize) -> String {
+    fn calc_windows(rec: Sequence, repeat: &str, ws: usize) -> String {
         let file = Vec::new();
         let mut lw = LineWriter::new(file);
-        let id = rec.id().to_owned();
-
-        write_window_counts(rec, &mut lw, repeat, ws, id, "tsv").unwrap();
+        write_window_counts(rec, &mut lw, repeat, ws, ws, SoftMask::Include, Output::Tsv).unwrap();
 
         // read file contents to new vec
         let mut out = Vec::new();
@@ -196,10 +567,181 @@ mod tests {
         let rows: Vec<&str> = windows_calculation.lines().collect();
 
         // three in first window
-        assert_eq!(rows[0], "test1\t20\t3\t0\tTTAGG");
+        assert_eq!(
+            rows[0],
+            "test1\t0\t20\t20\t3\t0\tTTAGG\t0\t0\t0.4500\t0.7500\t\t"
+        );
         // none in second
-        assert_eq!(rows[1], "test1\t40\t0\t0\tTTAGG");
+        assert_eq!(
+            rows[1],
+            "test1\t20\t40\t20\t0\t0\tTTAGG\t0\t0\t0.3500\t0.0000\t\t"
+        );
         // two in third
-        assert_eq!(rows[2], "test1\t52\t2\t0\tTTAGG");
+        assert_eq!(
+            rows[2],
+            "test1\t40\t52\t12\t2\t0\tTTAGG\t0\t0\t0.5000\t0.8333\t\t"
+        );
+    }
+
+    #[test]
+    fn test_bedgraph_overlapping_windows() {
+        let rec = make_record("test1", &b"TTAGG".repeat(10));
+        let mut lw = LineWriter::new(Vec::new());
+        let output = Output::BedGraph { normalise: false };
+        write_window_counts(rec, &mut lw, "TTAGG", 20, 10, SoftMask::Include, output).unwrap();
+        let bedgraph = String::from_utf8(lw.into_inner().unwrap()).unwrap();
+
+        // the central 10 bases of each window of 20
+        let intervals: Vec<(usize, usize)> = bedgraph
+            .lines()
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                (fields[1].parse().unwrap(), fields[2].parse().unwrap())
+            })
+            .collect();
+        assert_eq!(intervals, vec![(5, 15), (15, 25), (25, 35), (35, 45)]);
+        assert!(intervals.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+        assert!(bedgraph.starts_with("test1\t5\t15\t4\n"));
+    }
+
+    #[test]
+    fn test_search_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let fasta = dir.join("genome.fa");
+        std::fs::write(
+            &fasta,
+            ">chr1\nTTAGGTTAGGTTAGGCAGCATCACACTGATCA\n>chr2\nTTAGG\n",
+        )
+        .unwrap();
+
+        let config = SearchConfig::new(&fasta, "TTAGG", "test", dir)
+            .window(10)
+            .extension(Extension::BedGraph)
+            .hits(Some(HitsFormat::Bed));
+        let summary = search(&config).unwrap();
+        assert_eq!(summary.sequences.len(), 2);
+        assert_eq!(
+            (
+                summary.sequences[0].id.as_str(),
+                summary.sequences[0].length
+            ),
+            ("chr1", 32)
+        );
+        assert_eq!(summary.sequences[0].windows, 4);
+        assert_eq!(
+            summary.files,
+            vec![
+                dir.join("test_telomeric_repeat_windows.bedgraph"),
+                dir.join("test_telomeric_repeat_hits.bed")
+            ]
+        );
+        let bedgraph = std::fs::read_to_string(&summary.files[0]).unwrap();
+        assert_eq!(bedgraph.lines().next(), Some("chr1\t0\t10\t2"));
+
+        let config = SearchConfig::new(&fasta, "TTAGN", "test", dir);
+        assert!(matches!(search(&config), Err(Error::InvalidMotif(_))));
+    }
+
+    #[test]
+    fn test_find_hits() {
+        // two tandem forward copies, one reverse complement copy
+        let seq = b"TTAGGTTAGGCCCCCTAA";
+        let hits = find_hits(seq, "ttagg", false, false).unwrap();
+        assert_eq!(
+            hits,
+            vec![
+                Hit {
+                    start: 0,
+                    end: 5,
+                    strand: '+',
+                    copies: 1
+                },
+                Hit {
+                    start: 5,
+                    end: 10,
+                    strand: '+',
+                    copies: 1
+                },
+                Hit {
+                    start: 13,
+                    end: 18,
+                    strand: '-',
+                    copies: 1
+                },
+            ]
+        );
+
+        let merged = find_hits(seq, "TTAGG", true, false).unwrap();
+        assert_eq!(
+            merged[0],
+            Hit {
+                start: 0,
+                end: 10,
+                strand: '+',
+                copies: 2
+            }
+        );
+        assert_eq!(merged.len(), 2);
+
+        // the masked copy is left out
+        let hits = find_hits(b"TTAGGttagg", "TTAGG", true, true).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end), (0, 5));
+    }
+
+    #[test]
+    fn test_find_hits_overlapping() {
+        // AAAA overlaps itself, so only every other match is kept
+        let hits = find_hits(b"AAAAAAAAA", "AAAA", true, false).unwrap();
+        assert_eq!(
+            hits,
+            vec![Hit {
+                start: 0,
+                end: 8,
+                strand: '+',
+                copies: 2
+            }]
+        );
+        // and TTAA is its own reverse complement
+        let hits = find_hits(b"GTTAAG", "TTAA", false, false).unwrap();
+        assert_eq!(
+            hits,
+            vec![Hit {
+                start: 1,
+                end: 5,
+                strand: '.',
+                copies: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_write_hits() {
+        let hits = [Hit {
+            start: 0,
+            end: 7500,
+            strand: '+',
+            copies: 1500,
+        }];
+        let write = |id: &str, format: HitsFormat| {
+            let mut file = LineWriter::new(Vec::new());
+            write_hits(&mut file, id, 10000, &hits, "ttaggg", format).unwrap();
+            String::from_utf8(file.into_inner().unwrap()).unwrap()
+        };
+
+        // the BED score can't be more than 1000
+        assert_eq!(
+            write("chr1", HitsFormat::Bed),
+            "chr1\t0\t7500\tTTAGGG\t1000\t+\n"
+        );
+        // but the GFF3 keeps every copy, with the id escaped
+        assert_eq!(
+            write("chr1;a=b,c%", HitsFormat::Gff3),
+            "##sequence-region chr1%3Ba%3Db%2Cc%25 1 10000\n\
+             chr1%3Ba%3Db%2Cc%25\ttidk\ttelomeric_repeat\t1\t7500\t1500\t+\t.\t\
+             ID=chr1%3Ba%3Db%2Cc%25_hit1;Name=TTAGGG;copies=1500\n"
+        );
+        assert_eq!(escape_gff3("a\tb"), "a%09b");
     }
 }
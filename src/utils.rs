@@ -5,6 +5,8 @@ use bio::pattern_matching::bom::BOM;
 use bio::pattern_matching::kmp::KMP;
 use lexical_sort::{natural_lexical_cmp, StringSort};
 use std::cmp::min;
+use std::fs::File;
+use std::path::Path;
 
 /// This does the hard lifting in `tidk search` and `tidk find`
 /// take input putative telomeric repeat (motif) and search against
@@ -165,6 +167,12 @@ pub fn lex_min(dna_string: &str) -> String {
     strings[0].to_string()
 }
 
+/// Open a file, naming it in the error if it can't be.
+pub fn open_file(path: &Path) -> std::io::Result<File> {
+    File::open(path)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,715 @@
+use crate::{input::Sequences, utils, Error, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str;
+
+/// The version of the TSV written by `tidk search` and `tidk find`.
+/// Version 1 had no version line, and gave only the end of each window
+/// in a `window` column. Version 2 had no `masked_count` or
+/// `repeat_density`, and version 3 no masked repeat numbers.
+pub const TSV_VERSION: u32 = 4;
+
+/// The header of the TSV, after the version line.
+pub const TSV_HEADER: &str = "id\tstart\tend\twindow_len\tforward_repeat_number\treverse_repeat_number\ttelomeric_repeat\tn_count\tmasked_count\tgc_content\trepeat_density\tmasked_forward_repeat_number\tmasked_reverse_repeat_number";
+
+/// Write the version line and header of the TSV.
+pub fn write_tsv_header<W: Write>(file: &mut W) -> Result<()> {
+    writeln!(file, "#tidk_tsv_version={}", TSV_VERSION)?;
+    writeln!(file, "{}", TSV_HEADER)?;
+    Ok(())
+}
+
+/// Write a window as a row of the TSV. The GC content and repeat
+/// density are left empty if the window is all N, and the masked
+/// repeat numbers unless they were counted separately.
+pub fn write_tsv_row<W: Write>(file: &mut W, window: &WindowCount) -> Result<()> {
+    let proportion = |p: Option<f64>| p.map(|p| format!("{:.4}", p)).unwrap_or_default();
+    let count = |c: Option<usize>| c.map(|c| c.to_string()).unwrap_or_default();
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        window.id,
+        window.start,
+        window.end,
+        window.end - window.start,
+        window.forward,
+        window.reverse,
+        window.motif,
+        window.n_count,
+        window.masked_count,
+        proportion(window.gc_content()),
+        proportion(window.repeat_density()),
+        count(window.masked_forward),
+        count(window.masked_reverse)
+    )?;
+    Ok(())
+}
+
+/// How occurrences of the repeat in soft-masked (lower case) sequence
+/// are counted. An occurrence is masked if any of its bases are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SoftMask {
+    /// Count them with the rest.
+    #[default]
+    Include,
+    /// Leave them out.
+    Skip,
+    /// Count them apart from the unmasked occurrences.
+    Separate,
+}
+
+impl SoftMask {
+    /// From the value of `--soft-masked`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "include" => Ok(SoftMask::Include),
+            "skip" => Ok(SoftMask::Skip),
+            "separate" => Ok(SoftMask::Separate),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown soft-masked mode {}, expected include, skip or separate.",
+                name
+            ))),
+        }
+    }
+
+    /// The value of `--soft-masked`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SoftMask::Include => "include",
+            SoftMask::Skip => "skip",
+            SoftMask::Separate => "separate",
+        }
+    }
+}
+
+/// The output format of the window counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Extension {
+    /// The TSV, with counts and densities.
+    #[default]
+    Tsv,
+    /// The total count, or density, of each window.
+    BedGraph,
+    BigWig,
+}
+
+impl Extension {
+    /// From the value of `--extension`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "tsv" => Ok(Extension::Tsv),
+            "bedgraph" => Ok(Extension::BedGraph),
+            "bigwig" => Ok(Extension::BigWig),
+            _ => Err(Error::InvalidOption(format!(
+                "Unknown output format {}, expected tsv, bedgraph or bigwig.",
+                name
+            ))),
+        }
+    }
+
+    /// The file extension.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::Tsv => "tsv",
+            Extension::BedGraph => "bedgraph",
+            Extension::BigWig => "bigwig",
+        }
+    }
+}
+
+/// A sequence counted by `tidk search` or `tidk find`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceSummary {
+    pub id: String,
+    pub length: usize,
+    /// The windows counted, per telomeric repeat.
+    pub windows: usize,
+}
+
+/// What `tidk search` or `tidk find` counted, and the files written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowsSummary {
+    pub sequences: Vec<SequenceSummary>,
+    pub files: Vec<PathBuf>,
+}
+
+/// The occurrences of a telomeric repeat in a window of a sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowCount {
+    /// The ID of the sequence.
+    pub id: String,
+    /// 0-based start of the window.
+    pub start: usize,
+    /// Exclusive end of the window.
+    pub end: usize,
+    /// Occurrences of the repeat.
+    pub forward: usize,
+    /// Occurrences of its reverse complement.
+    pub reverse: usize,
+    /// Occurrences of the repeat in soft-masked sequence, if counted
+    /// apart from `forward`...
+    pub masked_forward: Option<usize>,
+    /// ...and of its reverse complement.
+    pub masked_reverse: Option<usize>,
+    /// N (gap) bases in the window.
+    pub n_count: usize,
+    /// Soft-masked (lower case) bases in the window, other than N.
+    pub masked_count: usize,
+    /// G and C bases in the window.
+    pub gc_count: usize,
+    /// The telomeric repeat counted, in upper case.
+    pub motif: String,
+}
+
+impl WindowCount {
+    /// The bases in the window which aren't N. Soft-masked bases are
+    /// callable, as telomeric arrays are often masked as simple repeats.
+    pub fn callable(&self) -> usize {
+        (self.end - self.start) - self.n_count
+    }
+
+    /// The proportion of G and C in the callable bases.
+    pub fn gc_content(&self) -> Option<f64> {
+        let callable = self.callable();
+        (callable > 0).then(|| self.gc_count as f64 / callable as f64)
+    }
+
+    /// The proportion of callable bases covered by some `occurrences` of a
+    /// repeat of `repeat_length`, so windows overlapping gaps aren't
+    /// diluted. `None` if the window is all N.
+    pub fn density(&self, occurrences: usize, repeat_length: usize) -> Option<f64> {
+        let callable = self.callable();
+        (callable > 0).then(|| ((occurrences * repeat_length) as f64 / callable as f64).min(1.0))
+    }
+
+    /// The density of the repeat on both strands, see
+    /// [`WindowCount::density()`].
+    pub fn repeat_density(&self) -> Option<f64> {
+        self.density(self.forward + self.reverse, self.motif.len())
+    }
+
+    /// The interval standing for this window in a bedGraph or bigWig
+    /// track, which can't overlap its neighbours. This is the window
+    /// itself, or the central `step` bases when windows overlap.
+    pub fn track_interval(&self, window_size: usize, step: usize) -> (usize, usize) {
+        if step >= window_size {
+            return (self.start, self.end);
+        }
+        let start = self.start + (window_size - step) / 2;
+        (start.min(self.end), (start + step).min(self.end))
+    }
+}
+
+/// The start of each window along a sequence of `length`, every `step`
+/// bases, up to the first window which reaches the end.
+fn window_starts(length: usize, window_size: usize, step: usize) -> impl Iterator<Item = usize> {
+    let last = length.saturating_sub(window_size).div_ceil(step) * step;
+    (0..=last)
+        .step_by(step)
+        .take_while(move |&start| start < length)
+}
+
+/// Count the occurrences of a telomeric repeat and its reverse
+/// complement in windows of `window_size`, every `step` bases. With a
+/// step equal to the window size, the windows tile the sequence.
+/// Occurrences in soft-masked sequence are counted as `soft_mask` says.
+///
+/// The sequence is scanned once, and each occurrence counted in every
+/// window it lies wholly within, so overlapping windows don't rescan
+/// their shared bases.
+pub fn count_windows(
+    id: &str,
+    sequence: &[u8],
+    telomeric_repeat: &str,
+    window_size: usize,
+    step: usize,
+    soft_mask: SoftMask,
+) -> Result<Vec<WindowCount>> {
+    if window_size == 0 || step == 0 {
+        return Err(Error::InvalidOption(
+            "The window size and step must be greater than zero.".to_string(),
+        ));
+    }
+    crate::error::check_motif(telomeric_repeat)?;
+    let upper = sequence.to_ascii_uppercase();
+    let upper = str::from_utf8(&upper)?;
+    let forward_telomeric_seq = telomeric_repeat.to_uppercase();
+    let reverse_telomeric_seq = utils::reverse_complement(&forward_telomeric_seq);
+    let length = forward_telomeric_seq.len();
+
+    let forward = utils::find_motifs(&forward_telomeric_seq, upper).indexes;
+    let reverse = utils::find_motifs(&reverse_telomeric_seq, upper).indexes;
+    let (forward, masked_forward) = split_masked(sequence, forward, length, soft_mask);
+    let (reverse, masked_reverse) = split_masked(sequence, reverse, length, soft_mask);
+    let mut forward = Occurrences::new(&forward, length);
+    let mut reverse = Occurrences::new(&reverse, length);
+    let mut masked_forward = Occurrences::new(&masked_forward, length);
+    let mut masked_reverse = Occurrences::new(&masked_reverse, length);
+    let mut composition = Composition::new(sequence);
+
+    Ok(window_starts(sequence.len(), window_size, step)
+        .map(|start| {
+            let end = (start + window_size).min(sequence.len());
+            let bases = composition.count(start, end);
+            WindowCount {
+                id: id.to_string(),
+                start,
+                end,
+                forward: forward.count(start, end),
+                reverse: reverse.count(start, end),
+                masked_forward: (soft_mask == SoftMask::Separate)
+                    .then(|| masked_forward.count(start, end)),
+                masked_reverse: (soft_mask == SoftMask::Separate)
+                    .then(|| masked_reverse.count(start, end)),
+                n_count: bases.n,
+                masked_count: bases.masked,
+                gc_count: bases.gc,
+                motif: forward_telomeric_seq.clone(),
+            }
+        })
+        .collect())
+}
+
+/// The window counts of each sequence in turn, for each telomeric
+/// repeat, yielded as each sequence is read. Only the windows of one
+/// sequence are held at a time.
+pub struct WindowCounts {
+    sequences: Sequences,
+    telomeric_repeats: Vec<String>,
+    window_size: usize,
+    step: usize,
+    soft_mask: SoftMask,
+    counted: std::vec::IntoIter<WindowCount>,
+}
+
+impl WindowCounts {
+    /// Count `telomeric_repeats` in windows of `sequences`, as
+    /// [`count_windows()`] does.
+    pub fn new(
+        sequences: Sequences,
+        telomeric_repeats: Vec<String>,
+        window_size: usize,
+        step: usize,
+        soft_mask: SoftMask,
+    ) -> Self {
+        Self {
+            sequences,
+            telomeric_repeats,
+            window_size,
+            step,
+            soft_mask,
+            counted: Vec::new().into_iter(),
+        }
+    }
+
+    /// Count the windows of the next sequence.
+    fn count_next(&mut self) -> Option<Result<Vec<WindowCount>>> {
+        let sequence = match self.sequences.next()? {
+            Ok(sequence) => sequence,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut counted = Vec::new();
+        for telomeric_repeat in &self.telomeric_repeats {
+            match count_windows(
+                &sequence.id,
+                &sequence.seq,
+                telomeric_repeat,
+                self.window_size,
+                self.step,
+                self.soft_mask,
+            ) {
+                Ok(windows) => counted.extend(windows),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(counted))
+    }
+}
+
+impl Iterator for WindowCounts {
+    type Item = Result<WindowCount>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(window) = self.counted.next() {
+                return Some(Ok(window));
+            }
+            match self.count_next()? {
+                Ok(counted) => self.counted = counted.into_iter(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Split the occurrences into those counted as unmasked, and those in
+/// soft-masked sequence counted apart (if any).
+fn split_masked(
+    sequence: &[u8],
+    indexes: Vec<usize>,
+    length: usize,
+    soft_mask: SoftMask,
+) -> (Vec<usize>, Vec<usize>) {
+    if soft_mask == SoftMask::Include {
+        return (indexes, Vec::new());
+    }
+    let (masked, unmasked): (Vec<usize>, Vec<usize>) = indexes.into_iter().partition(|&index| {
+        sequence[index..index + length]
+            .iter()
+            .any(u8::is_ascii_lowercase)
+    });
+    match soft_mask {
+        SoftMask::Separate => (unmasked, masked),
+        _ => (unmasked, Vec::new()),
+    }
+}
+
+/// Counts of the kinds of base in part of a sequence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Bases {
+    n: usize,
+    masked: usize,
+    gc: usize,
+}
+
+impl Bases {
+    fn add(&mut self, base: u8) {
+        if base == b'N' || base == b'n' {
+            self.n += 1;
+            return;
+        }
+        if base.is_ascii_lowercase() {
+            self.masked += 1;
+        }
+        if matches!(base, b'G' | b'g' | b'C' | b'c') {
+            self.gc += 1;
+        }
+    }
+}
+
+/// The base counts of windows which move along a sequence, from running
+/// totals up to the window start and end, so each base is read twice
+/// however much the windows overlap.
+struct Composition<'a> {
+    sequence: &'a [u8],
+    /// Position and counts of the bases before it, for the start...
+    start: (usize, Bases),
+    /// ...and end of the window.
+    end: (usize, Bases),
+}
+
+impl<'a> Composition<'a> {
+    fn new(sequence: &'a [u8]) -> Self {
+        Self {
+            sequence,
+            start: (0, Bases::default()),
+            end: (0, Bases::default()),
+        }
+    }
+
+    /// The base counts of `start..end`. Windows must be visited in order.
+    fn count(&mut self, start: usize, end: usize) -> Bases {
+        for (position, target) in [(&mut self.start, start), (&mut self.end, end)] {
+            for &base in &self.sequence[position.0..target] {
+                position.1.add(base);
+            }
+            position.0 = target;
+        }
+        Bases {
+            n: self.end.1.n - self.start.1.n,
+            masked: self.end.1.masked - self.start.1.masked,
+            gc: self.end.1.gc - self.start.1.gc,
+        }
+    }
+}
+
+/// Sorted occurrence positions, counted in windows which move along
+/// the sequence.
+struct Occurrences<'a> {
+    indexes: &'a [usize],
+    length: usize,
+    /// The first occurrence starting at or after the window start.
+    first: usize,
+    /// The first occurrence ending after the window end.
+    last: usize,
+}
+
+impl<'a> Occurrences<'a> {
+    fn new(indexes: &'a [usize], length: usize) -> Self {
+        Self {
+            indexes,
+            length,
+            first: 0,
+            last: 0,
+        }
+    }
+
+    /// The number of occurrences wholly within `start..end`. Windows must
+    /// be visited in order.
+    fn count(&mut self, start: usize, end: usize) -> usize {
+        while self.first < self.indexes.len() && self.indexes[self.first] < start {
+            self.first += 1;
+        }
+        while self.last < self.indexes.len() && self.indexes[self.last] + self.length <= end {
+            self.last += 1;
+        }
+        self.last.saturating_sub(self.first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // count each tile on its own, as `search` used to
+    fn count_chunks(sequence: &[u8], repeat: &str, window_size: usize) -> Vec<(usize, usize)> {
+        let reverse = utils::reverse_complement(repeat);
+        sequence
+            .chunks(window_size)
+            .map(|window| {
+                let window = str::from_utf8(window).unwrap().to_uppercase();
+                (
+                    utils::find_motifs(repeat, &window).indexes.len(),
+                    utils::find_motifs(&reverse, &window).indexes.len(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tiles_match_chunks() {
+        let sequence = b"TTAGGTTAGGttaggCAGCATCCTAACCTAACATCTGATTAGGTTAGGTTAGG";
+        for window_size in [1, 5, 7, 20, 52, 100] {
+            let counts: Vec<(usize, usize)> = count_windows(
+                "chr1",
+                sequence,
+                "TTAGG",
+                window_size,
+                window_size,
+                SoftMask::Include,
+            )
+            .unwrap()
+            .iter()
+            .map(|w| (w.forward, w.reverse))
+            .collect();
+            assert_eq!(counts, count_chunks(sequence, "TTAGG", window_size));
+        }
+    }
+
+    #[test]
+    fn test_sliding_windows() {
+        // an array straddling the boundary between tiles
+        let sequence = b"CCCCCCCTTAGGTTAGGCCC";
+        let tiles = count_windows("chr1", sequence, "TTAGG", 10, 10, SoftMask::Include).unwrap();
+        assert_eq!(
+            tiles.iter().map(|w| w.forward).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        let sliding = count_windows("chr1", sequence, "TTAGG", 10, 5, SoftMask::Include).unwrap();
+        assert_eq!(
+            sliding,
+            vec![
+                WindowCount {
+                    id: "chr1".to_string(),
+                    start: 0,
+                    end: 10,
+                    forward: 0,
+                    reverse: 0,
+                    masked_forward: None,
+                    masked_reverse: None,
+                    n_count: 0,
+                    masked_count: 0,
+                    gc_count: 7,
+                    motif: "TTAGG".to_string(),
+                },
+                WindowCount {
+                    id: "chr1".to_string(),
+                    start: 5,
+                    end: 15,
+                    forward: 1,
+                    reverse: 0,
+                    masked_forward: None,
+                    masked_reverse: None,
+                    n_count: 0,
+                    masked_count: 0,
+                    gc_count: 4,
+                    motif: "TTAGG".to_string(),
+                },
+                WindowCount {
+                    id: "chr1".to_string(),
+                    start: 10,
+                    end: 20,
+                    forward: 1,
+                    reverse: 0,
+                    masked_forward: None,
+                    masked_reverse: None,
+                    n_count: 0,
+                    masked_count: 0,
+                    gc_count: 7,
+                    motif: "TTAGG".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let fasta = dir.path().join("genome.fa");
+        std::fs::write(&fasta, ">chr1\nTTAGGTTAGGCCCCC\n>chr2\nCCTAA\n").unwrap();
+        let (_, sequences) = crate::input::read_sequences(&fasta, None).unwrap();
+        let windows: Vec<WindowCount> = WindowCounts::new(
+            sequences,
+            vec!["ttagg".to_string(), "TTAGGG".to_string()],
+            10,
+            10,
+            SoftMask::Include,
+        )
+        .collect::<Result<_>>()
+        .unwrap();
+        let counts: Vec<(&str, usize, &str, usize, usize)> = windows
+            .iter()
+            .map(|w| {
+                (
+                    w.id.as_str(),
+                    w.start,
+                    w.motif.as_str(),
+                    w.forward,
+                    w.reverse,
+                )
+            })
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("chr1", 0, "TTAGG", 2, 0),
+                ("chr1", 10, "TTAGG", 0, 0),
+                ("chr1", 0, "TTAGGG", 0, 0),
+                ("chr1", 10, "TTAGGG", 0, 0),
+                ("chr2", 0, "TTAGG", 0, 1),
+                ("chr2", 0, "TTAGGG", 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_starts() {
+        assert_eq!(window_starts(20, 10, 5).collect::<Vec<_>>(), vec![0, 5, 10]);
+        // the last window is cut short
+        assert_eq!(
+            window_starts(22, 10, 5).collect::<Vec<_>>(),
+            vec![0, 5, 10, 15]
+        );
+        assert_eq!(window_starts(5, 10, 3).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(window_starts(0, 10, 3).count(), 0);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(SoftMask::from_name("skip").unwrap(), SoftMask::Skip);
+        assert_eq!(Extension::from_name("bigwig").unwrap(), Extension::BigWig);
+        // a typo is an error, not the default
+        assert!(matches!(
+            SoftMask::from_name("skpi"),
+            Err(Error::InvalidOption(_))
+        ));
+        assert!(Extension::from_name("bed").is_err());
+    }
+
+    #[test]
+    fn test_track_interval() {
+        let window = WindowCount {
+            id: "chr1".to_string(),
+            start: 10,
+            end: 20,
+            forward: 0,
+            reverse: 0,
+            masked_forward: None,
+            masked_reverse: None,
+            n_count: 0,
+            masked_count: 0,
+            gc_count: 0,
+            motif: "TTAGG".to_string(),
+        };
+        assert_eq!(window.track_interval(10, 10), (10, 20));
+        assert_eq!(window.track_interval(10, 4), (13, 17));
+    }
+
+    #[test]
+    fn test_composition() {
+        let sequence = b"NNNNGCgcATatNNcc";
+        let windows = count_windows("chr1", sequence, "TTAGG", 4, 2, SoftMask::Include).unwrap();
+        let bases: Vec<(usize, usize, usize)> = windows
+            .iter()
+            .map(|w| (w.n_count, w.masked_count, w.gc_count))
+            .collect();
+        assert_eq!(
+            bases,
+            vec![
+                (4, 0, 0),
+                (2, 0, 2),
+                (0, 2, 4),
+                (0, 2, 2),
+                (0, 2, 0),
+                (2, 2, 0),
+                (2, 2, 2)
+            ]
+        );
+        assert_eq!(windows[0].gc_content(), None);
+        assert_eq!(windows[1].gc_content(), Some(1.0));
+        assert_eq!(windows[4].gc_content(), Some(0.0));
+
+        let mut row = Vec::new();
+        write_tsv_row(&mut row, &windows[3]).unwrap();
+        assert_eq!(
+            String::from_utf8(row).unwrap(),
+            "chr1\t6\t10\t4\t0\t0\tTTAGG\t0\t2\t0.5000\t0.0000\t\t\n"
+        );
+    }
+
+    #[test]
+    fn test_density() {
+        // half of the window is a gap
+        let sequence = b"TTAGGttaggNNNNNNNNNN";
+        let window = count_windows("chr1", sequence, "TTAGG", 20, 20, SoftMask::Include)
+            .unwrap()
+            .remove(0);
+        assert_eq!((window.n_count, window.masked_count), (10, 5));
+        assert_eq!(window.density(window.forward, 5), Some(1.0));
+        assert_eq!(window.density(1, 5), Some(0.5));
+
+        let gap = count_windows("chr1", b"NNNN", "TTAGG", 4, 4, SoftMask::Include)
+            .unwrap()
+            .remove(0);
+        assert_eq!(gap.density(0, 5), None);
+    }
+
+    #[test]
+    fn test_soft_mask() {
+        // an unmasked copy, a masked copy, one partly masked reverse
+        // complement, and a masked one
+        let sequence = b"TTAGGttaggCCTAaCCTAACCtaa";
+        let counts = |soft_mask| {
+            let window = count_windows("chr1", sequence, "TTAGG", 25, 25, soft_mask)
+                .unwrap()
+                .remove(0);
+            (
+                window.forward,
+                window.reverse,
+                window.masked_forward,
+                window.masked_reverse,
+            )
+        };
+        assert_eq!(counts(SoftMask::Include), (2, 3, None, None));
+        assert_eq!(counts(SoftMask::Skip), (1, 1, None, None));
+        assert_eq!(counts(SoftMask::Separate), (1, 1, Some(1), Some(2)));
+
+        let window = count_windows("chr1", sequence, "TTAGG", 25, 25, SoftMask::Separate)
+            .unwrap()
+            .remove(0);
+        let mut row = Vec::new();
+        write_tsv_row(&mut row, &window).unwrap();
+        assert!(String::from_utf8(row).unwrap().ends_with("\t1\t2\n"));
+    }
+}
@@ -0,0 +1,319 @@
+//! Reads the bigWig files of `BigWigWriter` back as a genome browser
+//! would, following the layout in Kent et al. (2010): the chromosome is
+//! looked up in the B+ tree, and the blocks overlapping a region are
+//! found through the R tree of the data or of a zoom level.
+
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::path::Path;
+use tidk::bigwig::BigWigWriter;
+
+/// A bigWig read into memory.
+struct BigWig {
+    bytes: Vec<u8>,
+    compressed: bool,
+    chromosome_tree: usize,
+    full_index: usize,
+    summary: usize,
+    /// The reduction and index offset of each zoom level.
+    zoom_levels: Vec<(u32, usize)>,
+}
+
+/// A record of a zoom level.
+#[derive(Debug, PartialEq)]
+struct ZoomRecord {
+    start: u32,
+    end: u32,
+    bases: u32,
+    min: f32,
+    max: f32,
+    sum: f32,
+}
+
+impl BigWig {
+    fn open(path: &Path) -> Self {
+        let bytes = std::fs::read(path).unwrap();
+        let mut bigwig = BigWig {
+            bytes,
+            compressed: false,
+            chromosome_tree: 0,
+            full_index: 0,
+            summary: 0,
+            zoom_levels: Vec::new(),
+        };
+        assert_eq!(bigwig.u32(0), 0x888F_FC26, "not a bigWig");
+        assert!(bigwig.u16(4) >= 3);
+        bigwig.chromosome_tree = bigwig.u64(8) as usize;
+        bigwig.full_index = bigwig.u64(24) as usize;
+        bigwig.summary = bigwig.u64(44) as usize;
+        bigwig.compressed = bigwig.u32(52) > 0;
+        for level in 0..bigwig.u16(6) as usize {
+            let header = 64 + level * 24;
+            bigwig
+                .zoom_levels
+                .push((bigwig.u32(header), bigwig.u64(header + 16) as usize));
+        }
+        bigwig
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes(self.bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn f64(&self, offset: usize) -> f64 {
+        f64::from_le_bytes(self.bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    /// The bases covered, min, max and sum of the whole file.
+    fn total_summary(&self) -> (u64, f64, f64, f64) {
+        (
+            self.u64(self.summary),
+            self.f64(self.summary + 8),
+            self.f64(self.summary + 16),
+            self.f64(self.summary + 24),
+        )
+    }
+
+    /// The id and length of a chromosome, searching the B+ tree.
+    fn chromosome(&self, name: &str) -> Option<(u32, u32)> {
+        let tree = self.chromosome_tree;
+        assert_eq!(self.u32(tree), 0x78CA_8C91);
+        let key_size = self.u32(tree + 8) as usize;
+        if name.len() > key_size {
+            return None;
+        }
+        let mut key = name.as_bytes().to_vec();
+        key.resize(key_size, 0);
+
+        let mut node = tree + 32;
+        loop {
+            let leaf = self.bytes[node] == 1;
+            let count = self.u16(node + 2) as usize;
+            let items = node + 4;
+            let item_key = |i: usize| {
+                let offset = items + i * (key_size + 8);
+                &self.bytes[offset..offset + key_size]
+            };
+            if leaf {
+                return (0..count).find(|&i| item_key(i) == key).map(|i| {
+                    let value = items + i * (key_size + 8) + key_size;
+                    (self.u32(value), self.u32(value + 4))
+                });
+            }
+            // the last child whose first key is not after the name
+            let child = (0..count).rev().find(|&i| item_key(i) <= &key[..])?;
+            node = self.u64(items + child * (key_size + 8) + key_size) as usize;
+        }
+    }
+
+    /// The offset and size of the blocks in an R tree which overlap a
+    /// region of a chromosome.
+    fn blocks(&self, index: usize, chromosome: u32, start: u32, end: u32) -> Vec<(usize, usize)> {
+        assert_eq!(self.u32(index), 0x2468_ACE0);
+        let overlaps = |node: usize| {
+            let (start_chromosome, start_base) = (self.u32(node), self.u32(node + 4));
+            let (end_chromosome, end_base) = (self.u32(node + 8), self.u32(node + 12));
+            (start_chromosome, start_base) < (chromosome, end)
+                && (end_chromosome, end_base) > (chromosome, start)
+        };
+
+        let mut blocks = Vec::new();
+        let mut nodes = vec![index + 48];
+        while let Some(node) = nodes.pop() {
+            let leaf = self.bytes[node] == 1;
+            let count = self.u16(node + 2) as usize;
+            for i in 0..count {
+                if leaf {
+                    let item = node + 4 + i * 32;
+                    if overlaps(item) {
+                        blocks.push((self.u64(item + 16) as usize, self.u64(item + 24) as usize));
+                    }
+                } else {
+                    let item = node + 4 + i * 24;
+                    if overlaps(item) {
+                        nodes.push(self.u64(item + 16) as usize);
+                    }
+                }
+            }
+        }
+        blocks.sort();
+        blocks
+    }
+
+    fn block(&self, (offset, size): (usize, usize)) -> Vec<u8> {
+        let bytes = &self.bytes[offset..offset + size];
+        if !self.compressed {
+            return bytes.to_vec();
+        }
+        let mut block = Vec::new();
+        ZlibDecoder::new(bytes).read_to_end(&mut block).unwrap();
+        block
+    }
+
+    /// The intervals and values overlapping a region of a chromosome.
+    fn values(&self, name: &str, start: u32, end: u32) -> Vec<(u32, u32, f32)> {
+        let (chromosome, _) = self.chromosome(name).unwrap();
+        let mut values = Vec::new();
+        for block in self.blocks(self.full_index, chromosome, start, end) {
+            let block = self.block(block);
+            let field =
+                |offset: usize| u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+            let value =
+                |offset: usize| f32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+            let (section_start, step, span) = (field(4), field(12), field(16));
+            let count = u16::from_le_bytes([block[22], block[23]]) as usize;
+            assert_eq!(field(0), chromosome);
+            for i in 0..count {
+                let item = match block[20] {
+                    // bedGraph
+                    1 => (field(24 + i * 12), field(28 + i * 12), value(32 + i * 12)),
+                    // variable step
+                    2 => {
+                        let item_start = field(24 + i * 8);
+                        (item_start, item_start + span, value(28 + i * 8))
+                    }
+                    // fixed step
+                    3 => {
+                        let item_start = section_start + i as u32 * step;
+                        (item_start, item_start + span, value(24 + i * 4))
+                    }
+                    section => panic!("unknown section type {}", section),
+                };
+                if item.0 < end && item.1 > start {
+                    values.push(item);
+                }
+            }
+        }
+        values
+    }
+
+    /// The records of a zoom level overlapping a region of a chromosome.
+    fn zoom(&self, level: usize, name: &str, start: u32, end: u32) -> Vec<ZoomRecord> {
+        let (chromosome, _) = self.chromosome(name).unwrap();
+        let mut records = Vec::new();
+        for block in self.blocks(self.zoom_levels[level].1, chromosome, start, end) {
+            let block = self.block(block);
+            for record in block.chunks_exact(32) {
+                let field = |offset: usize| {
+                    u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+                };
+                let value = |offset: usize| {
+                    f32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+                };
+                if field(0) == chromosome && field(4) < end && field(8) > start {
+                    records.push(ZoomRecord {
+                        start: field(4),
+                        end: field(8),
+                        bases: field(12),
+                        min: value(16),
+                        max: value(20),
+                        sum: value(24),
+                    });
+                }
+            }
+        }
+        records
+    }
+}
+
+/// The windows of chr1, enough for several blocks of data.
+fn chr1() -> Vec<(u32, u32, f32)> {
+    (0..5000)
+        .map(|i| (i * 10, i * 10 + 10, (i % 7) as f32))
+        .collect()
+}
+
+#[test]
+fn test_read_bigwig() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counts.bw");
+    let mut writer = BigWigWriter::create(&path, 100).unwrap();
+    // not in sorted order, as from a FASTA
+    writer.add_chromosome("chr2", 25).unwrap();
+    writer.add(0, 10, 3.0).unwrap();
+    writer.add(10, 20, 0.0).unwrap();
+    writer.add(20, 30, 1.5).unwrap();
+    writer.add_chromosome("chr1", 50000).unwrap();
+    for (start, end, value) in chr1() {
+        writer.add(start, end, value).unwrap();
+    }
+    writer.add_chromosome("chrUn_1", 40).unwrap();
+    writer.add(0, 40, 2.0).unwrap();
+    writer.finish().unwrap();
+
+    let bigwig = BigWig::open(&path);
+    assert_eq!(bigwig.chromosome("chr1"), Some((1, 50000)));
+    assert_eq!(bigwig.chromosome("chr2"), Some((0, 25)));
+    assert_eq!(bigwig.chromosome("chrUn_1"), Some((2, 40)));
+    assert_eq!(bigwig.chromosome("chr3"), None);
+
+    // the last window is clipped to the chromosome
+    assert_eq!(
+        bigwig.values("chr2", 0, 25),
+        vec![(0, 10, 3.0), (10, 20, 0.0), (20, 25, 1.5)]
+    );
+    assert_eq!(bigwig.values("chrUn_1", 10, 20), vec![(0, 40, 2.0)]);
+
+    // a range over the boundary of two blocks of data
+    let blocks = bigwig.blocks(bigwig.full_index, 1, 10000, 10500);
+    assert_eq!(blocks.len(), 2);
+    let expected: Vec<_> = chr1()
+        .into_iter()
+        .filter(|&(start, end, _)| start < 10500 && end > 10000)
+        .collect();
+    assert_eq!(bigwig.values("chr1", 10000, 10500), expected);
+    // and the whole of chr1, from every block
+    assert_eq!(bigwig.values("chr1", 0, 50000), chr1());
+
+    // zoom levels of 100, 400, 1600 ... bases
+    let reductions: Vec<u32> = bigwig.zoom_levels.iter().map(|z| z.0).collect();
+    assert_eq!(&reductions[..3], &[100, 400, 1600]);
+    for (level, &reduction) in reductions.iter().enumerate() {
+        let records = bigwig.zoom(level, "chr1", 0, 50000);
+        assert_eq!(records.len(), 50000_usize.div_ceil(reduction as usize));
+        for record in records {
+            let values: Vec<f32> = chr1()
+                .into_iter()
+                .filter(|&(start, end, _)| start < record.end && end > record.start)
+                .map(|(_, _, value)| value)
+                .collect();
+            assert_eq!(record.bases, values.len() as u32 * 10);
+            assert_eq!(
+                record.min,
+                values.iter().cloned().fold(f32::INFINITY, f32::min)
+            );
+            assert_eq!(record.max, values.iter().cloned().fold(0.0, f32::max));
+            assert_eq!(record.sum, values.iter().sum::<f32>() * 10.0);
+        }
+    }
+    // zoom records are clipped to the chromosome
+    assert_eq!(
+        bigwig.zoom(0, "chr2", 0, 25),
+        vec![ZoomRecord {
+            start: 0,
+            end: 25,
+            bases: 25,
+            min: 0.0,
+            max: 3.0,
+            sum: 37.5
+        }]
+    );
+    assert_eq!(bigwig.zoom(1, "chrUn_1", 0, 40)[0].sum, 80.0);
+
+    let chr1_sum: f64 = chr1()
+        .iter()
+        .map(|&(_, _, value)| value as f64 * 10.0)
+        .sum();
+    assert_eq!(
+        bigwig.total_summary(),
+        (50000 + 25 + 40, 0.0, 6.0, chr1_sum + 37.5 + 80.0)
+    );
+}
@@ -0,0 +1,162 @@
+//! The C API, driven from the C harness in tests/capi/harness.c. The
+//! harness is compiled against the shared library, which the tests build
+//! with `cargo rustc --crate-type cdylib`, using the C compiler in `CC`,
+//! or `cc`.
+#![cfg(feature = "capi")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use tempfile::TempDir;
+use tidk::windows::{count_windows, SoftMask};
+
+/// The crate root, where the header and harness are.
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Build the shared library, once, into its own target directory so as
+/// not to wait on the build of this test.
+fn library() -> &'static Path {
+    static LIBRARY: OnceLock<PathBuf> = OnceLock::new();
+    LIBRARY.get_or_init(|| {
+        let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+        let status = Command::new(env!("CARGO"))
+            .args([
+                "rustc",
+                "--lib",
+                "--features",
+                "capi",
+                "--crate-type",
+                "cdylib",
+            ])
+            .arg("--manifest-path")
+            .arg(root().join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target)
+            .status()
+            .unwrap();
+        assert!(status.success(), "Could not build the shared library");
+        target.join("debug").join(format!(
+            "{}tidk{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ))
+    })
+}
+
+/// Compile the harness into `dir`, linked to the shared library.
+fn harness(dir: &Path) -> PathBuf {
+    let harness = dir.join("harness");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .arg(root().join("tests/capi/harness.c"))
+        .arg("-I")
+        .arg(root().join("include"))
+        .args(["-Wall", "-Werror", "-o"])
+        .arg(&harness)
+        // by its path, so the harness loads this library and no other
+        .arg(library())
+        .status()
+        .unwrap_or_else(|e| panic!("Could not run the C compiler {}: {}", compiler, e));
+    assert!(status.success(), "Could not compile the C harness");
+    harness
+}
+
+/// The stdout of the harness, and whether it succeeded.
+fn run(harness: &Path, args: &[&str]) -> (bool, String) {
+    let output = Command::new(harness).args(args).output().unwrap();
+    // a harness which couldn't start says why on stderr
+    assert!(
+        output.status.code().is_some_and(|code| code <= 1),
+        "The harness failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_scan() {
+    let dir = TempDir::new().unwrap();
+    let harness = harness(dir.path());
+    let fasta = dir.path().join("genome.fa");
+    let records = [
+        ("chr1", "TTAGGTTAGGTTAGGCAGCATCACACTGATCACCTAACCTAA"),
+        ("chr2", "GATTACATTAGG"),
+    ];
+    let text: String = records
+        .iter()
+        .map(|(id, seq)| format!(">{}\n{}\n", id, seq))
+        .collect();
+    std::fs::write(&fasta, text).unwrap();
+    let fasta = fasta.to_str().unwrap();
+
+    // the same windows as the library counts
+    let mut expected = String::new();
+    for (id, seq) in records {
+        for window in count_windows(id, seq.as_bytes(), "TTAGG", 10, 10, SoftMask::Include).unwrap()
+        {
+            expected.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                window.id, window.start, window.end, window.forward, window.reverse
+            ));
+        }
+    }
+    let (success, out) = run(&harness, &["scan", fasta, "TTAGG", "10"]);
+    assert!(success);
+    assert_eq!(out.lines().count(), 7);
+    assert_eq!(out, expected);
+    assert!(out.starts_with("chr1\t0\t10\t2\t0\n"));
+
+    // the error codes are those of the command line
+    let missing = dir.path().join("missing.fa");
+    let (success, out) = run(
+        &harness,
+        &["scan", missing.to_str().unwrap(), "TTAGG", "10"],
+    );
+    assert!(!success);
+    assert!(out.starts_with("error\t3\t"));
+    assert!(out.contains("missing.fa"));
+    let error = |motif: &str, window_size: &str| {
+        let (success, out) = run(&harness, &["scan", fasta, motif, window_size]);
+        assert!(!success);
+        out.split('\t').nth(1).unwrap().to_string()
+    };
+    assert_eq!(error("TTAGN", "10"), "6");
+    assert_eq!(error("TTAGG", "0"), "2");
+}
+
+#[test]
+fn test_null_arguments() {
+    let dir = TempDir::new().unwrap();
+    let harness = harness(dir.path());
+    assert_eq!(run(&harness, &["null"]), (true, String::new()));
+}
+
+/// include/tidk.h is checked in, so must match src/capi.rs. Run with
+/// `TIDK_UPDATE_HEADER=1` to regenerate it.
+#[test]
+fn test_header_is_current() {
+    let config = cbindgen::Config::from_file(root().join("cbindgen.toml")).unwrap();
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(root())
+        .with_config(config)
+        .generate()
+        .expect("the C API can be written as C")
+        .write(&mut header);
+    let header = String::from_utf8(header).unwrap();
+
+    let path = root().join("include/tidk.h");
+    if std::env::var_os("TIDK_UPDATE_HEADER").is_some() {
+        std::fs::write(&path, &header).unwrap();
+    }
+    assert!(
+        std::fs::read_to_string(&path).unwrap() == header,
+        "include/tidk.h is out of date, regenerate it with \
+         TIDK_UPDATE_HEADER=1 cargo test --features capi --test capi"
+    );
+}
@@ -0,0 +1,71 @@
+/*
+ Drives the C API for tests/capi.rs, which compiles it against the
+ shared library.
+
+   harness scan <path> <motif> <window size>
+     prints a line per window, as "id\tstart\tend\tforward\treverse",
+     or on error "error\t<code>\t<message>", exiting with 1.
+   harness null
+     checks null pointers are errors, not crashes.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "tidk.h"
+
+static int print_error(void) {
+  const char *message = tidk_last_error_message();
+  printf("error\t%d\t%s\n", tidk_last_error_code(), message == NULL ? "" : message);
+  return 1;
+}
+
+static int scan(const char *path, const char *motif, size_t window_size) {
+  TidkFasta *fasta = tidk_fasta_open(path);
+  if (fasta == NULL) {
+    return print_error();
+  }
+  TidkScan *scan = tidk_scan(fasta, motif, window_size);
+  if (scan == NULL) {
+    tidk_fasta_free(fasta);
+    return print_error();
+  }
+
+  TidkWindow window;
+  int status;
+  while ((status = tidk_scan_next(scan, &window)) == 1) {
+    printf("%s\t%zu\t%zu\t%zu\t%zu\n", window.id, window.start, window.end, window.forward,
+           window.reverse);
+  }
+  tidk_scan_free(scan);
+  tidk_fasta_free(fasta);
+  return status == -1 ? print_error() : 0;
+}
+
+static int null_arguments(void) {
+  TidkWindow window;
+  if (tidk_fasta_open(NULL) != NULL || tidk_last_error_code() != 2) {
+    return 1;
+  }
+  if (tidk_scan(NULL, "TTAGGG", 100) != NULL) {
+    return 1;
+  }
+  if (tidk_scan_next(NULL, &window) != -1) {
+    return 1;
+  }
+  tidk_scan_free(NULL);
+  tidk_fasta_free(NULL);
+  return 0;
+}
+
+int main(int argc, char **argv) {
+  if (argc == 5 && strcmp(argv[1], "scan") == 0) {
+    return scan(argv[2], argv[3], strtoul(argv[4], NULL, 10));
+  }
+  if (argc == 2 && strcmp(argv[1], "null") == 0) {
+    return null_arguments();
+  }
+  fprintf(stderr, "usage: harness scan <path> <motif> <window size> | harness null\n");
+  return 2;
+}